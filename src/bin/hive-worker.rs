@@ -0,0 +1,11 @@
+//! Runs one worker's tools for a `hive` process that isolates it (`isolated_workers`): one JSON tool call
+//! per line on stdin, one JSON result per line on stdout
+
+use anyhow::{Result, anyhow};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let role = std::env::args().nth(1).ok_or_else(|| anyhow!("usage: hive-worker <role>"))?;
+    // Tools may block on async work (web searches), which needs a runtime thread that allows it
+    tokio::task::block_in_place(|| hive::isolation::serve(&role))
+}
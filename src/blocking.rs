@@ -0,0 +1,11 @@
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Run blocking `work` from synchronous tool code without starving the async runtime
+/// On a multi-threaded runtime the thread is handed over first so other tasks keep running; a
+/// current-thread runtime (`#[tokio::test]`, or an embedder's) can't do that, so there `work` just runs
+pub fn block_in_place<T>(work: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => tokio::task::block_in_place(work),
+        _ => work(),
+    }
+}
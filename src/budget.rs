@@ -0,0 +1,74 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use crate::{config, Message};
+
+tokio::task_local! {
+    /// When the current delegation is abandoned, for tools that wait on something
+    static DEADLINE: Instant;
+}
+
+/// Run `future` (a delegation) knowing it will be cancelled at `deadline`, if it has one
+pub async fn with_deadline<F: Future>(deadline: Option<Instant>, future: F) -> F::Output {
+    match deadline {
+        Some(deadline) => DEADLINE.scope(deadline, future).await,
+        None => future.await,
+    }
+}
+
+/// The current delegation's deadline; a tool blocking its thread must return by then, since the timeout can't fire until it does
+pub fn deadline() -> Option<Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// Requests sent to Ollama by any agent since startup
+static REQUESTS_SENT: AtomicUsize = AtomicUsize::new(0);
+
+pub fn count_request() {
+    REQUESTS_SENT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Request and wall-clock limits for one `run_agentic_loop`, shared with the workers it delegates to
+#[derive(Debug)]
+pub struct LoopBudget {
+    started: Instant,
+    requests_at_start: usize,
+    max_requests: usize,
+    max_duration: Duration,
+}
+
+impl LoopBudget {
+    pub fn from_config() -> Self {
+        let config = config::get();
+        LoopBudget {
+            started: Instant::now(),
+            requests_at_start: REQUESTS_SENT.load(Ordering::Relaxed),
+            max_requests: config.max_loop_requests,
+            max_duration: Duration::from_secs(config.max_loop_secs),
+        }
+    }
+
+    /// Describe which limit was hit, or `None` while there's budget left
+    pub fn exhausted(&self) -> Option<String> {
+        let used = REQUESTS_SENT.load(Ordering::Relaxed) - self.requests_at_start;
+        if used >= self.max_requests {
+            return Some(format!("request budget of {} exhausted", self.max_requests));
+        }
+        if self.started.elapsed() >= self.max_duration {
+            return Some(format!("time budget of {}s exhausted", self.max_duration.as_secs()));
+        }
+        None
+    }
+}
+
+/// The most useful answer available when a loop is cut short: the last assistant text, else the last tool result
+pub fn best_so_far(messages: &[Message], reason: &str) -> String {
+    let answer = messages
+        .iter()
+        .rev()
+        .filter(|m| m.role == "assistant" || m.role == "tool")
+        .filter_map(|m| m.content.as_deref())
+        .find(|c| !c.trim().is_empty())
+        .unwrap_or("(no answer was produced)");
+    format!("{}\n\n[Stopped early: {}]", answer, reason)
+}
@@ -0,0 +1,74 @@
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use serde::Serialize;
+use crate::Message;
+
+/// Session-scoped store of responses keyed by a hash of the full request
+static RESPONSES: OnceLock<Mutex<HashMap<u64, Message>>> = OnceLock::new();
+
+fn responses() -> &'static Mutex<HashMap<u64, Message>> {
+    RESPONSES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Hash a request by its serialized form, which covers model, messages and tools
+pub fn request_key<T: Serialize>(request: &T) -> Option<u64> {
+    let body = serde_json::to_string(request).ok()?;
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    Some(hasher.finish())
+}
+
+pub fn get(key: u64) -> Option<Message> {
+    responses().lock().ok()?.get(&key).cloned()
+}
+
+pub fn insert(key: u64, message: Message) {
+    if let Ok(mut map) = responses().lock() {
+        map.insert(key, message);
+    }
+}
+
+/// Worker answers keyed by `analysis_key`, each stored with the hash of the files it was based on
+static ANALYSES: OnceLock<Mutex<HashMap<u64, (u64, String)>>> = OnceLock::new();
+
+fn analyses() -> &'static Mutex<HashMap<u64, (u64, String)>> {
+    ANALYSES.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn analysis_key(worker: &str, instruction: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (worker, instruction).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash the paths and contents of `files`; `None` if any can't be read
+pub fn hash_files(files: &[PathBuf]) -> Option<u64> {
+    let mut hasher = DefaultHasher::new();
+    for file in files {
+        file.hash(&mut hasher);
+        fs::read(file).ok()?.hash(&mut hasher);
+    }
+    Some(hasher.finish())
+}
+
+/// The cached answer for `key` if its files still hash to `inputs`; a stale entry is dropped
+pub fn get_analysis(key: u64, inputs: u64) -> Option<String> {
+    let mut map = analyses().lock().ok()?;
+    match map.get(&key) {
+        Some((hashed, answer)) if *hashed == inputs => Some(answer.clone()),
+        Some(_) => {
+            map.remove(&key);
+            None
+        }
+        None => None,
+    }
+}
+
+pub fn insert_analysis(key: u64, inputs: u64, answer: String) {
+    if let Ok(mut map) = analyses().lock() {
+        map.insert(key, (inputs, answer));
+    }
+}
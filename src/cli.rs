@@ -0,0 +1,122 @@
+use std::env;
+use std::path::PathBuf;
+use anyhow::{Result, bail};
+use hive::config::{DelegationMode, HiveConfig};
+
+/// Command-line flags; anything set here overrides the config files and environment
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub cache_requests: bool,
+    /// Rewrite the full message history to this file after every turn
+    pub dump_messages: Option<PathBuf>,
+    /// Resume the conversation saved here (if any) and keep it updated after every turn
+    pub session: Option<PathBuf>,
+    /// Append each turn's delegation trace to this file as a JSON line
+    pub delegation_trace: Option<PathBuf>,
+    /// Ask the Queen for a recap of the session before exiting
+    pub summary: bool,
+    /// Let the Queen ask clarifying questions before delegating
+    pub clarify: bool,
+    /// Skip preloading the Queen's model at startup
+    pub no_warm_up: bool,
+    /// Keep the Queen's model loaded for the whole session
+    pub pin_queen: bool,
+    /// Echo each worker's instruction and result to stdout
+    pub show_work: bool,
+    /// Have the Queen double-check its answers before returning them
+    pub verify: bool,
+    /// Skip the startup banner listing workers and their access
+    pub quiet: bool,
+    /// Print the estimated context size after every turn
+    pub context_report: bool,
+    /// Have the Queen's plan approved before any worker runs
+    pub plan_approve: bool,
+    /// Check that files workers report writing actually exist
+    pub verify_writes: bool,
+    /// Have the Queen answer directly, without delegating to workers
+    pub no_delegation: bool,
+    /// Run every agent on this model instead of its own
+    pub model_override: Option<String>,
+    /// Send every agent's requests to this Ollama chat URL
+    pub backend_override: Option<String>,
+}
+
+impl CliArgs {
+    pub fn parse() -> Result<Self> {
+        let mut parsed = CliArgs::default();
+        let mut args = env::args().skip(1);
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--cache-requests" => parsed.cache_requests = true,
+                "--summary" => parsed.summary = true,
+                "--clarify" => parsed.clarify = true,
+                "--no-warm-up" => parsed.no_warm_up = true,
+                "--pin-queen" => parsed.pin_queen = true,
+                "--show-work" => parsed.show_work = true,
+                "--verify" => parsed.verify = true,
+                "--quiet" => parsed.quiet = true,
+                "--context-report" => parsed.context_report = true,
+                "--plan-approve" => parsed.plan_approve = true,
+                "--verify-writes" => parsed.verify_writes = true,
+                "--no-delegation" => parsed.no_delegation = true,
+                "--model-override" => parsed.model_override = Some(value(&mut args, &arg)?),
+                "--backend-override" => parsed.backend_override = Some(value(&mut args, &arg)?),
+                "--session" => parsed.session = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--delegation-trace" => parsed.delegation_trace = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--dump-messages" => parsed.dump_messages = Some(PathBuf::from(value(&mut args, &arg)?)),
+                other => bail!("Unknown argument: {}", other),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Layer the flags over a config loaded from the config files and environment
+    pub fn apply(&self, config: &mut HiveConfig) {
+        if self.cache_requests {
+            config.cache_requests = true;
+        }
+        if self.clarify {
+            config.clarify_first = true;
+        }
+        if self.no_warm_up {
+            config.warm_up = false;
+        }
+        if self.pin_queen {
+            config.pin_queen_model = true;
+        }
+        if self.show_work {
+            config.show_work = true;
+        }
+        if self.verify {
+            config.verify_answers = true;
+        }
+        if self.context_report {
+            config.report_context = true;
+        }
+        if self.plan_approve {
+            config.plan_approve = true;
+        }
+        if self.verify_writes {
+            config.verify_writes = true;
+        }
+        if self.no_delegation {
+            config.delegation = DelegationMode::Off;
+        }
+        if let Some(model) = &self.model_override {
+            config.model_override = Some(model.clone());
+        }
+        if let Some(url) = &self.backend_override {
+            config.backend_override = Some(url.clone());
+        }
+    }
+}
+
+/// Take the value following a flag, failing clearly when it's missing
+fn value(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String> {
+    match args.next() {
+        Some(value) => Ok(value),
+        None => bail!("{} requires a value", flag),
+    }
+}
@@ -0,0 +1,876 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::{env, fs};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+static CONFIG: OnceLock<HiveConfig> = OnceLock::new();
+
+/// Directory in the workspace holding project settings, layered over the global ones
+const PROJECT_DIR: &str = ".hive";
+const CONFIG_FILE: &str = "config.toml";
+
+/// A named Ollama endpoint (typically one per GPU) and the models it hosts
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Backend {
+    pub name: String,
+    pub url: String,
+    pub models: Vec<String>,
+}
+
+/// An extra HTTP header sent with model requests; the value is kept out of `Debug` output since it's usually a secret
+#[derive(Clone, Serialize, Deserialize)]
+pub struct HttpHeader {
+    pub name: String,
+    pub value: String,
+}
+
+impl std::fmt::Debug for HttpHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: <redacted>", self.name)
+    }
+}
+
+/// Whether the Queen is offered its tools (delegation and the rest) on a turn
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DelegationMode {
+    On,
+    /// The Queen answers directly from its own knowledge and the conversation
+    Off,
+    /// Tools only for turns that don't read as plain conversation
+    Auto,
+}
+
+impl FromStr for DelegationMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "on" => Ok(DelegationMode::On),
+            "off" => Ok(DelegationMode::Off),
+            "auto" => Ok(DelegationMode::Auto),
+            other => Err(format!("unknown delegation mode '{}' (expected on, off or auto)", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for DelegationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DelegationMode::On => "on",
+            DelegationMode::Off => "off",
+            DelegationMode::Auto => "auto",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Runtime settings shared by the Queen and all workers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HiveConfig {
+    /// Command run by the `check_code` tool; must emit cargo-style JSON diagnostics
+    pub check_command: String,
+    /// Wall-clock seconds the check command may run before it's killed; 0 means no limit
+    pub check_timeout_secs: u64,
+    /// Reuse responses for byte-identical requests instead of calling Ollama again
+    pub cache_requests: bool,
+    /// Extra backend URLs serving each model; requests go to the least-loaded one
+    pub model_replicas: HashMap<String, Vec<String>>,
+    /// Named backends agents can refer to via `Agent::backend`
+    pub backends: Vec<Backend>,
+    /// Total bytes a single multi-file read may return before truncating
+    pub max_read_bytes: usize,
+    /// Upper bound on operations accepted by a single `batch` call
+    pub max_batch_ops: usize,
+    /// Largest result a single read/list call may return before asking for a narrower call
+    pub max_result_bytes: usize,
+    /// How many file mutations to keep snapshots for; 0 disables the undo log
+    pub undo_history: usize,
+    /// Directory mutating tools are confined to; reads still span the whole working directory
+    pub write_root: Option<PathBuf>,
+    /// Ollama `keep_alive` per model; the `*` entry applies to models not listed
+    pub keep_alive: HashMap<String, String>,
+    /// Models to try, in order, when an agent's own model isn't pulled or fails to load
+    pub fallback_models: HashMap<String, Vec<String>>,
+    /// Give the Queen an `ask_user` tool and tell it to clarify ambiguous requests first
+    pub clarify_first: bool,
+    /// Tools each worker may expose, keyed by role; workers not listed keep all their tools
+    pub worker_tools: HashMap<String, Vec<String>>,
+    /// Most Ollama requests (Queen and workers combined) a single user turn may make
+    pub max_loop_requests: usize,
+    /// Most wall-clock seconds a single user turn may take
+    pub max_loop_secs: u64,
+    /// Decode non-UTF8 text lossily (with a warning) instead of refusing to read it
+    pub lossy_utf8: bool,
+    /// Expose the file manager's `set_permissions` tool; off by default since it can make files executable
+    pub allow_set_permissions: bool,
+    /// Most wall-clock seconds a single delegation may take before it's abandoned; 0 disables the limit
+    pub delegation_timeout_secs: u64,
+    /// Preload the Queen's model at startup so the first turn doesn't wait for it
+    pub warm_up: bool,
+    /// Keep the Queen's model loaded indefinitely (`keep_alive: -1`), overriding `keep_alive`
+    pub pin_queen_model: bool,
+    /// Print each delegation's instruction and (truncated) result to stdout as the loop runs
+    pub show_work: bool,
+    /// Environment variables the `get_env` tool may reveal; anything else stays hidden
+    pub env_allowlist: Vec<String>,
+    /// Most tool calls run from a single model response; the rest are skipped. 0 disables the cap
+    pub max_tool_calls_per_turn: usize,
+    /// Search provider for the web search worker: `searxng` (default) or `brave`
+    pub search_provider: String,
+    /// Base URL of the SearXNG instance to query
+    pub search_url: Option<String>,
+    /// API key for providers that need one
+    pub search_api_key: Option<String>,
+    /// Directory each worker is confined to, keyed by role; workers not listed use the working directory
+    pub worker_roots: HashMap<String, PathBuf>,
+    /// Size the Queen's history is pruned to (oldest exchanges first) before each turn; 0 disables pruning
+    pub max_context_bytes: usize,
+    /// Have the Queen re-check its final answer against the tool results (costs one extra request)
+    pub verify_answers: bool,
+    /// Worker roles whose results get reasoning tags and lone code fences stripped
+    pub clean_output: Vec<String>,
+    /// CPU seconds each shell command, Python snippet or check command may use before it's killed; 0 means no limit
+    pub shell_max_cpu_secs: u64,
+    /// Virtual memory (MB) each shell command, Python snippet or check command may use; 0 means no limit
+    pub shell_max_memory_mb: u64,
+    /// Bytes a shell command, Python snippet or check command may write to stdout or stderr before it's killed; 0 means no limit
+    pub shell_max_output_bytes: usize,
+    /// Interpreter the Python runner executes snippets with
+    pub python_interpreter: String,
+    /// Wall-clock seconds a Python snippet may run before it's killed; 0 means no limit
+    pub python_timeout_secs: u64,
+    /// How many times a worker cut off by the length limit is asked to continue its answer
+    pub max_continuations: usize,
+    /// How many times a worker in JSON mode is re-asked after answering with invalid JSON
+    pub max_json_retries: usize,
+    /// Seconds a single Ollama request may take, keyed by `queen` or worker role; `*` covers the rest. 0 means no limit
+    pub request_timeout_secs: HashMap<String, u64>,
+    /// Times a request that timed out or hit a server error is retried, keyed like `request_timeout_secs`
+    pub request_retries: HashMap<String, u32>,
+    /// Let the shell's process tools see and signal any process, not just those hive started
+    pub manage_all_processes: bool,
+    /// Context window (tokens) the Queen's model runs with in Ollama; only used to report how full it is
+    pub num_ctx: usize,
+    /// Print the estimated context size after every turn
+    pub report_context: bool,
+    /// Extra HTTP headers for model requests, keyed by `queen` or worker role; `*` applies to every agent
+    pub headers: HashMap<String, Vec<HttpHeader>>,
+    /// Let file tools follow symlinks that lead outside the working directory
+    pub follow_symlinks: bool,
+    /// Style the Queen answers the user in (e.g. "Respond tersely, prefer bullet points"); workers are unaffected
+    pub persona: Option<String>,
+    /// How many recent worker results the Queen's `recall` tool keeps; 0 removes the tool
+    pub recall_size: usize,
+    /// Have the Queen write a plan the user approves (or amends) before any worker runs
+    pub plan_approve: bool,
+    /// Times per turn the Queen may send a worker nearly the same instruction before the loop is broken; 0 disables the check
+    pub max_similar_delegations: usize,
+    /// Worker roles whose system prompt goes without the generic reasoning block (the Queen never gets it)
+    pub skip_reasoning: Vec<String>,
+    /// Model every agent uses instead of its own, for quick single-machine runs
+    pub model_override: Option<String>,
+    /// Ollama chat URL every agent sends to instead of its own or its backend's
+    pub backend_override: Option<String>,
+    /// Worker roles whose read-only answers are reused while the files their instruction names are unchanged
+    pub analysis_cache: Vec<String>,
+    /// After a delegation, check that the files the worker says it wrote exist and flag the ones that don't
+    pub verify_writes: bool,
+    /// Seconds quitting (or Ctrl-C) waits for in-flight delegations and tool calls before exiting anyway
+    pub shutdown_timeout_secs: u64,
+    /// Commands whose output is added to the Queen's system prompt at startup, run like the shell worker's commands
+    pub startup_context_commands: Vec<String>,
+    /// Seconds each startup context command may run before it's killed
+    pub startup_context_timeout_secs: u64,
+    /// Workspace files read (concurrently) at startup and given to the Queen, e.g. `Cargo.toml,README.md,src/main.rs`
+    pub prefetch_files: Vec<String>,
+    /// Most prefetch reads in flight at once
+    pub prefetch_parallelism: usize,
+    /// Whether the Queen may delegate: always (default), never, or only on turns that seem to need it
+    pub delegation: DelegationMode,
+    /// Commands the shell's `command_help` tool may run with `--help`; empty disables the tool
+    pub help_commands: Vec<String>,
+    /// Where `command_help` gets usage from: `help` (`<cmd> --help`, the default), `man`, or `auto` (`--help`, then man)
+    pub help_source: String,
+    /// Workers whose tools run in a `hive-worker` helper process, so a crash or runaway tool can't take hive down,
+    /// e.g. `shell,python_runner`. Their undo history stays in the helper
+    pub isolated_workers: Vec<String>,
+    /// Seconds an isolated tool call may take before its helper is killed; 0 waits indefinitely
+    pub isolation_timeout_secs: u64,
+    /// Settings taken from a config file or the environment rather than the defaults, for `/config`
+    pub settings: Vec<Setting>,
+}
+
+/// One setting `HiveConfig::load` found, and which file (or the environment) it came from
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Setting {
+    pub name: String,
+    pub value: String,
+    pub source: String,
+}
+
+impl Default for HiveConfig {
+    fn default() -> Self {
+        HiveConfig {
+            check_command: "cargo clippy --message-format=json".to_string(),
+            check_timeout_secs: 600,
+            cache_requests: false,
+            model_replicas: HashMap::new(),
+            backends: Vec::new(),
+            max_read_bytes: 100_000,
+            max_batch_ops: 20,
+            max_result_bytes: 32_000,
+            undo_history: 20,
+            write_root: None,
+            keep_alive: HashMap::new(),
+            fallback_models: HashMap::new(),
+            clarify_first: false,
+            worker_tools: HashMap::new(),
+            max_loop_requests: 50,
+            max_loop_secs: 600,
+            lossy_utf8: true,
+            allow_set_permissions: false,
+            delegation_timeout_secs: 300,
+            warm_up: true,
+            pin_queen_model: false,
+            show_work: false,
+            env_allowlist: ["PATH", "HOME", "USER", "SHELL", "PWD", "LANG", "TERM", "CARGO_HOME", "RUSTUP_HOME"]
+                .map(str::to_string)
+                .to_vec(),
+            max_tool_calls_per_turn: 5,
+            search_provider: "searxng".to_string(),
+            search_url: None,
+            search_api_key: None,
+            worker_roots: HashMap::new(),
+            max_context_bytes: 200_000,
+            verify_answers: false,
+            clean_output: Vec::new(),
+            shell_max_cpu_secs: 0,
+            shell_max_memory_mb: 0,
+            shell_max_output_bytes: 10_000_000,
+            python_interpreter: "python3".to_string(),
+            python_timeout_secs: 30,
+            max_continuations: 2,
+            max_json_retries: 2,
+            // The Queen's larger model is much slower per request than the workers'
+            request_timeout_secs: HashMap::from([("queen".to_string(), 600), ("*".to_string(), 120)]),
+            request_retries: HashMap::from([("queen".to_string(), 1), ("*".to_string(), 2)]),
+            manage_all_processes: false,
+            num_ctx: 4096,
+            report_context: false,
+            headers: HashMap::new(),
+            follow_symlinks: false,
+            persona: None,
+            recall_size: 20,
+            plan_approve: false,
+            max_similar_delegations: 3,
+            skip_reasoning: Vec::new(),
+            model_override: None,
+            backend_override: None,
+            analysis_cache: Vec::new(),
+            verify_writes: false,
+            shutdown_timeout_secs: 10,
+            startup_context_commands: Vec::new(),
+            startup_context_timeout_secs: 10,
+            prefetch_files: Vec::new(),
+            prefetch_parallelism: 4,
+            delegation: DelegationMode::On,
+            help_commands: DEFAULT_HELP_COMMANDS.iter().map(|command| command.to_string()).collect(),
+            help_source: "help".to_string(),
+            isolated_workers: Vec::new(),
+            isolation_timeout_secs: 300,
+            settings: Vec::new(),
+        }
+    }
+}
+
+impl HiveConfig {
+    /// Build the config from defaults overridden, in increasing priority, by the global config file
+    /// (`~/.config/hive/config.toml`), the project's `.hive/config.toml`, and `HIVE_*` environment variables (and `.env`)
+    /// File keys are the variable names without `HIVE_`, lowercased: `max_loop_secs = 900`, `verify = true`
+    pub fn load() -> Self {
+        let _ = dotenvy::dotenv();
+        let sources = Sources::load();
+        let mut config = HiveConfig::default();
+
+        if let Some(command) = sources.var("HIVE_CHECK_COMMAND") {
+            config.check_command = command;
+        }
+        if let Some(secs) = sources.parse("HIVE_CHECK_TIMEOUT_SECS") {
+            config.check_timeout_secs = secs;
+        }
+        if let Some(enabled) = sources.flag("HIVE_CACHE_REQUESTS") {
+            config.cache_requests = enabled;
+        }
+        if let Some(replicas) = sources.var("HIVE_MODEL_REPLICAS") {
+            config.model_replicas = parse_list_map(&replicas);
+        }
+        if let Some(backends) = sources.var("HIVE_BACKENDS") {
+            config.backends = parse_backends(&backends);
+        }
+        if let Some(bytes) = sources.parse("HIVE_MAX_READ_BYTES") {
+            config.max_read_bytes = bytes;
+        }
+        if let Some(ops) = sources.parse("HIVE_MAX_BATCH_OPS") {
+            config.max_batch_ops = ops;
+        }
+        if let Some(bytes) = sources.parse("HIVE_MAX_RESULT_BYTES") {
+            config.max_result_bytes = bytes;
+        }
+        if let Some(depth) = sources.parse("HIVE_UNDO_HISTORY") {
+            config.undo_history = depth;
+        }
+        if let Some(root) = sources.var("HIVE_WRITE_ROOT") {
+            config.write_root = Some(PathBuf::from(root));
+        }
+        if let Some(keep_alive) = sources.var("HIVE_KEEP_ALIVE") {
+            config.keep_alive = parse_keep_alive(&keep_alive);
+        }
+        if let Some(fallbacks) = sources.var("HIVE_FALLBACK_MODELS") {
+            config.fallback_models = parse_list_map(&fallbacks);
+        }
+        if let Some(enabled) = sources.flag("HIVE_CLARIFY_FIRST") {
+            config.clarify_first = enabled;
+        }
+        if let Some(tools) = sources.var("HIVE_WORKER_TOOLS") {
+            config.worker_tools = parse_list_map(&tools);
+        }
+        if let Some(requests) = sources.parse("HIVE_MAX_LOOP_REQUESTS") {
+            config.max_loop_requests = requests;
+        }
+        if let Some(secs) = sources.parse("HIVE_MAX_LOOP_SECS") {
+            config.max_loop_secs = secs;
+        }
+        if let Some(lossy) = sources.flag("HIVE_LOSSY_UTF8") {
+            config.lossy_utf8 = lossy;
+        }
+        if let Some(enabled) = sources.flag("HIVE_ALLOW_SET_PERMISSIONS") {
+            config.allow_set_permissions = enabled;
+        }
+        if let Some(secs) = sources.parse("HIVE_DELEGATION_TIMEOUT_SECS") {
+            config.delegation_timeout_secs = secs;
+        }
+        if let Some(enabled) = sources.flag("HIVE_WARM_UP") {
+            config.warm_up = enabled;
+        }
+        if let Some(enabled) = sources.flag("HIVE_PIN_QUEEN_MODEL") {
+            config.pin_queen_model = enabled;
+        }
+        if let Some(enabled) = sources.flag("HIVE_SHOW_WORK") {
+            config.show_work = enabled;
+        }
+        if let Some(names) = sources.var("HIVE_ENV_ALLOWLIST") {
+            config.env_allowlist = parse_list(&names);
+        }
+        if let Some(calls) = sources.parse("HIVE_MAX_TOOL_CALLS_PER_TURN") {
+            config.max_tool_calls_per_turn = calls;
+        }
+        if let Some(provider) = sources.var("HIVE_SEARCH_PROVIDER") {
+            config.search_provider = provider.trim().to_ascii_lowercase();
+        }
+        if let Some(url) = sources.var("HIVE_SEARCH_URL") {
+            config.search_url = Some(url);
+        }
+        if let Some(key) = sources.var("HIVE_SEARCH_API_KEY") {
+            config.search_api_key = Some(key);
+        }
+        if let Some(roots) = sources.var("HIVE_WORKER_ROOTS") {
+            config.worker_roots = parse_map(&roots)
+                .into_iter()
+                .map(|(role, root)| (role, PathBuf::from(root)))
+                .collect();
+        }
+        if let Some(bytes) = sources.parse("HIVE_MAX_CONTEXT_BYTES") {
+            config.max_context_bytes = bytes;
+        }
+        if let Some(enabled) = sources.flag("HIVE_VERIFY") {
+            config.verify_answers = enabled;
+        }
+        if let Some(roles) = sources.var("HIVE_CLEAN_OUTPUT") {
+            config.clean_output = parse_list(&roles);
+        }
+        if let Some(secs) = sources.parse("HIVE_SHELL_MAX_CPU_SECS") {
+            config.shell_max_cpu_secs = secs;
+        }
+        if let Some(mb) = sources.parse("HIVE_SHELL_MAX_MEMORY_MB") {
+            config.shell_max_memory_mb = mb;
+        }
+        if let Some(bytes) = sources.parse("HIVE_SHELL_MAX_OUTPUT_BYTES") {
+            config.shell_max_output_bytes = bytes;
+        }
+        if let Some(interpreter) = sources.var("HIVE_PYTHON") {
+            config.python_interpreter = interpreter;
+        }
+        if let Some(secs) = sources.parse("HIVE_PYTHON_TIMEOUT_SECS") {
+            config.python_timeout_secs = secs;
+        }
+        if let Some(count) = sources.parse("HIVE_MAX_CONTINUATIONS") {
+            config.max_continuations = count;
+        }
+        if let Some(count) = sources.parse("HIVE_MAX_JSON_RETRIES") {
+            config.max_json_retries = count;
+        }
+        if let Some(timeouts) = sources.var("HIVE_REQUEST_TIMEOUT_SECS") {
+            config.request_timeout_secs.extend(parse_numbers(&timeouts));
+        }
+        if let Some(retries) = sources.var("HIVE_REQUEST_RETRIES") {
+            config.request_retries.extend(parse_numbers(&retries));
+        }
+        if let Some(enabled) = sources.flag("HIVE_MANAGE_ALL_PROCESSES") {
+            config.manage_all_processes = enabled;
+        }
+        if let Some(tokens) = sources.parse("HIVE_NUM_CTX") {
+            config.num_ctx = tokens;
+        }
+        if let Some(enabled) = sources.flag("HIVE_REPORT_CONTEXT") {
+            config.report_context = enabled;
+        }
+        if let Some(headers) = sources.var("HIVE_HEADERS") {
+            config.headers = parse_headers(&headers);
+        }
+        // Shorthand for the common case of one API key for every backend
+        if let Some(token) = sources.var("HIVE_BEARER_TOKEN") {
+            config.headers.entry("*".to_string()).or_default().push(HttpHeader {
+                name: "Authorization".to_string(),
+                value: format!("Bearer {}", token.trim()),
+            });
+        }
+        if let Some(enabled) = sources.flag("HIVE_FOLLOW_SYMLINKS") {
+            config.follow_symlinks = enabled;
+        }
+        if let Some(persona) = sources.var("HIVE_PERSONA") {
+            config.persona = Some(persona).filter(|p| !p.trim().is_empty());
+        }
+        if let Some(size) = sources.parse("HIVE_RECALL_SIZE") {
+            config.recall_size = size;
+        }
+        if let Some(enabled) = sources.flag("HIVE_PLAN_APPROVE") {
+            config.plan_approve = enabled;
+        }
+        if let Some(count) = sources.parse("HIVE_MAX_SIMILAR_DELEGATIONS") {
+            config.max_similar_delegations = count;
+        }
+        if let Some(roles) = sources.var("HIVE_SKIP_REASONING") {
+            config.skip_reasoning = parse_list(&roles);
+        }
+        if let Some(model) = sources.var("HIVE_MODEL_OVERRIDE") {
+            config.model_override = Some(model.trim().to_string()).filter(|m| !m.is_empty());
+        }
+        if let Some(url) = sources.var("HIVE_BACKEND_OVERRIDE") {
+            config.backend_override = Some(url.trim().to_string()).filter(|u| !u.is_empty());
+        }
+        if let Some(roles) = sources.var("HIVE_ANALYSIS_CACHE") {
+            config.analysis_cache = parse_list(&roles);
+        }
+        if let Some(enabled) = sources.flag("HIVE_VERIFY_WRITES") {
+            config.verify_writes = enabled;
+        }
+        if let Some(secs) = sources.parse("HIVE_SHUTDOWN_TIMEOUT_SECS") {
+            config.shutdown_timeout_secs = secs;
+        }
+        if let Some(commands) = sources.lines("HIVE_STARTUP_CONTEXT_COMMANDS") {
+            config.startup_context_commands = commands;
+        }
+        if let Some(secs) = sources.parse("HIVE_STARTUP_CONTEXT_TIMEOUT_SECS") {
+            config.startup_context_timeout_secs = secs;
+        }
+        if let Some(files) = sources.var("HIVE_PREFETCH_FILES") {
+            config.prefetch_files = parse_list(&files);
+        }
+        if let Some(reads) = sources.parse("HIVE_PREFETCH_PARALLELISM") {
+            config.prefetch_parallelism = reads;
+        }
+        if let Some(mode) = sources.parse("HIVE_DELEGATION") {
+            config.delegation = mode;
+        }
+        if let Some(commands) = sources.var("HIVE_HELP_COMMANDS") {
+            config.help_commands = parse_list(&commands);
+        }
+        if let Some(source) = sources.var("HIVE_HELP_SOURCE") {
+            config.help_source = source.trim().to_ascii_lowercase();
+        }
+        if let Some(roles) = sources.var("HIVE_ISOLATED_WORKERS") {
+            config.isolated_workers = parse_list(&roles);
+        }
+        if let Some(secs) = sources.parse("HIVE_ISOLATION_TIMEOUT_SECS") {
+            config.isolation_timeout_secs = secs;
+        }
+
+        sources.warn_unused();
+        config.settings = sources.settings.into_inner();
+        config
+    }
+
+    /// The settings overriding the defaults and where each came from, with secrets hidden
+    pub fn report(&self) -> String {
+        if self.settings.is_empty() {
+            return format!(
+                "All settings are defaults (no HIVE_* variables, {}/{} or ~/.config/hive/{})",
+                PROJECT_DIR, CONFIG_FILE, CONFIG_FILE
+            );
+        }
+        let width = self.settings.iter().map(|s| s.name.len()).max().unwrap_or(0) - "HIVE_".len();
+        let mut lines = vec!["Settings (command-line flags apply on top; everything else is a default):".to_string()];
+        for setting in &self.settings {
+            let key = setting.name.trim_start_matches("HIVE_").to_ascii_lowercase();
+            let secret = ["KEY", "TOKEN", "HEADERS"].iter().any(|word| setting.name.contains(word));
+            let value = if secret { "<redacted>" } else { setting.value.as_str() };
+            lines.push(format!("  {:<width$} = {}  ({})", key, value, setting.source));
+        }
+        lines.join("\n")
+    }
+
+    pub fn tool_enabled(&self, role: &str, tool: &str) -> bool {
+        self.worker_tools
+            .get(role)
+            .is_none_or(|enabled| enabled.iter().any(|t| t == tool))
+    }
+
+    /// Root directory for `role`, or `None` to use the working directory
+    pub fn worker_root(&self, role: &str) -> Option<&Path> {
+        self.worker_roots.get(role).map(PathBuf::as_path)
+    }
+
+    pub fn keep_alive_for(&self, model: &str) -> Option<&str> {
+        self.keep_alive
+            .get(model)
+            .or_else(|| self.keep_alive.get("*"))
+            .map(String::as_str)
+    }
+
+    /// Per-request timeout for the Queen or a worker role, or `None` for no limit
+    pub fn request_timeout(&self, agent: &str) -> Option<Duration> {
+        let secs = self.request_timeout_secs.get(agent).or_else(|| self.request_timeout_secs.get("*"))?;
+        (*secs > 0).then(|| Duration::from_secs(*secs))
+    }
+
+    /// How many times to retry a failed request for the Queen or a worker role
+    pub fn request_retries_for(&self, agent: &str) -> u32 {
+        self.request_retries
+            .get(agent)
+            .or_else(|| self.request_retries.get("*"))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Headers for the Queen or a worker role: the `*` entries followed by the agent's own
+    pub fn headers_for(&self, agent: &str) -> Vec<(String, String)> {
+        ["*", agent]
+            .into_iter()
+            .filter_map(|key| self.headers.get(key))
+            .flatten()
+            .map(|header| (header.name.clone(), header.value.clone()))
+            .collect()
+    }
+
+    pub fn backend(&self, name: &str) -> Option<&Backend> {
+        self.backends.iter().find(|b| b.name == name)
+    }
+
+    /// Describe the model and backend overrides in effect, or `None` when agents use their own
+    pub fn override_warning(&self) -> Option<String> {
+        let mut overrides = Vec::new();
+        if let Some(model) = &self.model_override {
+            overrides.push(format!("every agent uses model {}", model));
+        }
+        if let Some(url) = &self.backend_override {
+            overrides.push(format!("every request goes to {}", url));
+        }
+        (!overrides.is_empty()).then(|| format!("OVERRIDES ACTIVE: {}", overrides.join("; ")))
+    }
+
+    /// Backends other than `exclude_url` that host `model`, in config order
+    /// None when a backend override pins every request to one URL
+    pub fn fallback_urls(&self, model: &str, exclude_url: &str) -> Vec<String> {
+        if self.backend_override.is_some() {
+            return Vec::new();
+        }
+        self.backends
+            .iter()
+            .filter(|b| b.url != exclude_url && b.models.iter().any(|m| m == model))
+            .map(|b| b.url.clone())
+            .collect()
+    }
+}
+
+/// Where `HiveConfig::load` reads settings from, highest priority first: the environment, then each config file
+struct Sources {
+    /// Settings from the project's and the global config file, in that order, keyed by variable name
+    files: Vec<(PathBuf, HashMap<String, toml::Value>)>,
+    /// Every setting found so far, for `/config`
+    settings: RefCell<Vec<Setting>>,
+}
+
+impl Sources {
+    fn load() -> Self {
+        let global = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+            .map(|dir| dir.join("hive").join(CONFIG_FILE));
+        let project = Some(Path::new(PROJECT_DIR).join(CONFIG_FILE));
+        let files = [project, global]
+            .into_iter()
+            .flatten()
+            .filter(|path| path.is_file())
+            .filter_map(|path| Some((path.clone(), read_config_file(&path)?)))
+            .collect();
+        Sources { files, settings: RefCell::new(Vec::new()) }
+    }
+
+    /// The raw value of a `HIVE_*` setting from the highest-priority source that has it
+    fn var(&self, name: &str) -> Option<String> {
+        let (value, source) = match env::var(name) {
+            Ok(value) => (value, "environment".to_string()),
+            Err(_) => {
+                let (value, path) = self.file_value(name)?;
+                let separator = if PIPE_SEPARATED.contains(&name) { "|" } else { "," };
+                (setting_value(value, separator), path.display().to_string())
+            }
+        };
+        self.record(name, &value, source);
+        Some(value)
+    }
+
+    /// A list whose items may contain commas (like shell commands): one per line in the environment,
+    /// or a TOML array in a config file
+    fn lines(&self, name: &str) -> Option<Vec<String>> {
+        let (items, source): (Vec<String>, _) = match env::var(name) {
+            Ok(value) => (value.lines().map(str::to_string).collect(), "environment".to_string()),
+            Err(_) => {
+                let (value, path) = self.file_value(name)?;
+                let items = match value {
+                    toml::Value::Array(items) => items.iter().map(|item| setting_value(item, ",")).collect(),
+                    other => vec![setting_value(other, ",")],
+                };
+                (items, path.display().to_string())
+            }
+        };
+        let items: Vec<String> = items.into_iter().map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect();
+        self.record(name, &items.join(" | "), source);
+        Some(items)
+    }
+
+    fn file_value(&self, name: &str) -> Option<(&toml::Value, &Path)> {
+        self.files.iter().find_map(|(path, values)| Some((values.get(name)?, path.as_path())))
+    }
+
+    fn record(&self, name: &str, value: &str, source: String) {
+        self.settings.borrow_mut().push(Setting { name: name.to_string(), value: value.to_string(), source });
+    }
+
+    /// Read a boolean setting ("1"/"true"/"yes" vs anything else)
+    fn flag(&self, name: &str) -> Option<bool> {
+        self.var(name)
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes"))
+    }
+
+    /// Read and parse a setting, ignoring values that don't parse
+    fn parse<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.var(name)?.trim().parse().ok()
+    }
+
+    /// Point out file keys no setting reads, which are almost always typos
+    fn warn_unused(&self) {
+        let settings = self.settings.borrow();
+        for (path, values) in &self.files {
+            for name in values.keys().filter(|name| !settings.iter().any(|s| &s.name == *name)) {
+                let key = name.trim_start_matches("HIVE_").to_ascii_lowercase();
+                eprintln!("[CONFIG] Warning: unknown setting '{}' in {}", key, path.display());
+            }
+        }
+    }
+}
+
+/// Read a config file, keying its values by the `HIVE_*` variable each one stands in for
+/// A file that doesn't parse is skipped with a warning
+fn read_config_file(path: &Path) -> Option<HashMap<String, toml::Value>> {
+    let parsed = fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| text.parse::<toml::Table>().map_err(|e| e.to_string()));
+    let table = match parsed {
+        Ok(table) => table,
+        Err(e) => {
+            eprintln!("[CONFIG] Warning: ignoring {}: {}", path.display(), e);
+            return None;
+        }
+    };
+    Some(
+        table
+            .into_iter()
+            .map(|(key, value)| (format!("HIVE_{}", key.to_ascii_uppercase()), value))
+            .collect(),
+    )
+}
+
+/// Settings whose lists are `|`-separated in the environment, because their items (header values) may contain commas
+const PIPE_SEPARATED: &[&str] = &["HIVE_HEADERS"];
+
+/// The string form the environment variable would have: arrays become `a<separator>b`, tables `key=value;key2=a,b`
+fn setting_value(value: &toml::Value, separator: &str) -> String {
+    match value {
+        toml::Value::String(text) => text.clone(),
+        toml::Value::Array(items) => items.iter().map(|item| setting_value(item, separator)).collect::<Vec<_>>().join(separator),
+        toml::Value::Table(table) => table
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, setting_value(value, separator)))
+            .collect::<Vec<_>>()
+            .join(";"),
+        other => other.to_string(),
+    }
+}
+
+/// Commands whose `--help` only prints usage; interpreters, shells and commands that run or change things given as arguments are left out
+const DEFAULT_HELP_COMMANDS: &[&str] = &[
+    "awk", "cargo", "cat", "curl", "cut", "date", "df", "diff", "du", "find", "git", "grep", "gzip", "head", "jq", "ls", "make",
+    "ps", "rg", "rustc", "rustup", "sed", "sort", "tail", "tar", "tr", "uniq", "unzip", "wc", "wget", "zip",
+];
+
+/// Parse `a,b,c` into a list, ignoring blanks
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Parse `key=a,b;key2=c` into a map of lists (replica URLs, fallback models)
+fn parse_list_map(value: &str) -> HashMap<String, Vec<String>> {
+    value
+        .split(';')
+        .filter_map(|entry| {
+            let (model, urls) = entry.split_once('=')?;
+            Some((model.trim().to_string(), parse_list(urls)))
+        })
+        .collect()
+}
+
+/// Parse `model=30m;*=-1`, or a bare value that applies to every model
+fn parse_keep_alive(value: &str) -> HashMap<String, String> {
+    if !value.contains('=') {
+        return HashMap::from([("*".to_string(), value.trim().to_string())]);
+    }
+    parse_map(value)
+}
+
+/// Parse `queen=600;*=120` into numbers, dropping entries that don't parse
+fn parse_numbers<T: FromStr>(value: &str) -> HashMap<String, T> {
+    parse_map(value)
+        .into_iter()
+        .filter_map(|(key, number)| Some((key, number.parse().ok()?)))
+        .collect()
+}
+
+/// Parse `queen=X-Api-Key: abc|X-Org: hive;*=Authorization: Bearer xyz`
+/// Entries without an agent name (`X-Api-Key: abc`) apply to every agent
+fn parse_headers(value: &str) -> HashMap<String, Vec<HttpHeader>> {
+    let mut headers: HashMap<String, Vec<HttpHeader>> = HashMap::new();
+    for entry in value.split(';').filter(|e| !e.trim().is_empty()) {
+        let (agent, list) = match entry.split_once('=') {
+            Some((agent, list)) if !agent.contains(':') => (agent.trim(), list),
+            _ => ("*", entry),
+        };
+        let parsed = list.split('|').filter_map(|header| {
+            let (name, value) = header.split_once(':')?;
+            Some(HttpHeader { name: name.trim().to_string(), value: value.trim().to_string() })
+        });
+        headers.entry(agent.to_string()).or_default().extend(parsed);
+    }
+    headers
+}
+
+/// Parse `key=value;key2=value2` into a map
+fn parse_map(value: &str) -> HashMap<String, String> {
+    value
+        .split(';')
+        .filter_map(|entry| {
+            let (key, value) = entry.split_once('=')?;
+            Some((key.trim().to_string(), value.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Parse `gpu0=url|model1,model2;gpu1=url|model3` into backend entries
+fn parse_backends(value: &str) -> Vec<Backend> {
+    value
+        .split(';')
+        .filter_map(|entry| {
+            let (name, rest) = entry.split_once('=')?;
+            let (url, models) = rest.split_once('|').unwrap_or((rest, ""));
+            Some(Backend {
+                name: name.trim().to_string(),
+                url: url.trim().to_string(),
+                models: models
+                    .split(',')
+                    .map(|m| m.trim().to_string())
+                    .filter(|m| !m.is_empty())
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+/// Install the config for this process; must run before the first `get()`
+pub fn init(config: HiveConfig) {
+    if CONFIG.set(config).is_err() {
+        eprintln!("[CONFIG] Warning: config already initialized, ignoring override");
+    }
+}
+
+/// Global config, loaded from the config files and environment on first access
+pub fn get() -> &'static HiveConfig {
+    CONFIG.get_or_init(HiveConfig::load)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `(name, value)` pairs, for comparing parsed headers
+    fn pairs(headers: &[HttpHeader]) -> Vec<(&str, &str)> {
+        headers.iter().map(|h| (h.name.as_str(), h.value.as_str())).collect()
+    }
+
+    #[test]
+    fn parses_lists_and_maps() {
+        assert_eq!(parse_list(" a, ,b ,"), ["a", "b"]);
+        assert_eq!(parse_list_map("m1=u1, u2; m2 = u3")["m2"], ["u3"]);
+        assert_eq!(
+            parse_map("a=1;broken; b = 2 "),
+            HashMap::from([("a".to_string(), "1".to_string()), ("b".to_string(), "2".to_string())])
+        );
+        assert_eq!(parse_numbers::<u64>("queen=600;*=soon;w=5"), HashMap::from([("queen".to_string(), 600), ("w".to_string(), 5)]));
+    }
+
+    #[test]
+    fn a_bare_keep_alive_applies_to_every_model() {
+        assert_eq!(parse_keep_alive(" 30m "), HashMap::from([("*".to_string(), "30m".to_string())]));
+        assert_eq!(parse_keep_alive("queen=-1;*=5m")["queen"], "-1");
+    }
+
+    #[test]
+    fn parses_headers_per_agent() {
+        let headers = parse_headers("queen=X-Api-Key: abc|X-Org: a,b;Authorization: Bearer xyz;;");
+        assert_eq!(pairs(&headers["queen"]), [("X-Api-Key", "abc"), ("X-Org", "a,b")]);
+        assert_eq!(pairs(&headers["*"]), [("Authorization", "Bearer xyz")]);
+    }
+
+    #[test]
+    fn header_arrays_from_a_config_file_stay_separate() {
+        let array = toml::Value::Array(vec!["X-Tags: a, b".into(), "X-Org: hive".into()]);
+        let headers = parse_headers(&setting_value(&array, "|"));
+        assert_eq!(pairs(&headers["*"]), [("X-Tags", "a, b"), ("X-Org", "hive")]);
+    }
+
+    #[test]
+    fn parses_backends() {
+        let backends = parse_backends("gpu0=http://a:11434/api/chat|m1, m2;gpu1=http://b:11434/api/chat");
+        assert_eq!(backends.len(), 2);
+        assert_eq!((backends[0].name.as_str(), backends[0].url.as_str()), ("gpu0", "http://a:11434/api/chat"));
+        assert_eq!(backends[0].models, ["m1", "m2"]);
+        assert!(backends[1].models.is_empty());
+    }
+}
@@ -0,0 +1,174 @@
+use std::ops::Range;
+use crate::Message;
+
+/// Drop the oldest exchanges until `messages` fits in `max_bytes`
+/// Messages go in whole groups (an assistant tool call with all its results), so no tool
+/// result is ever left without the call that produced it. The leading system prompt and
+/// everything from the latest user message on are always kept. Returns how many messages were dropped.
+pub fn prune(messages: &mut Vec<Message>, max_bytes: usize) -> usize {
+    let mut total: usize = messages.iter().map(size).sum();
+    if max_bytes == 0 || total <= max_bytes {
+        return 0;
+    }
+
+    let start = usize::from(messages.first().is_some_and(|m| m.role == "system"));
+    let latest = messages.iter().rposition(|m| m.role == "user").unwrap_or(messages.len());
+    let mut end = start;
+    for group in groups(messages, start..latest.max(start)) {
+        if total <= max_bytes {
+            break;
+        }
+        total -= messages[group.clone()].iter().map(size).sum::<usize>();
+        end = group.end;
+    }
+    messages.drain(start..end);
+    end - start
+}
+
+/// Rough bytes per token for English text and JSON
+pub const BYTES_PER_TOKEN: usize = 4;
+
+/// Estimated tokens in a conversation, split by where they come from
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ContextUsage {
+    pub system: usize,
+    pub history: usize,
+    pub tool_results: usize,
+}
+
+impl ContextUsage {
+    pub fn total(&self) -> usize {
+        self.system + self.history + self.tool_results
+    }
+
+    /// One line for the end of a turn, e.g. `context: ~1200/4096 tokens (29%)`
+    pub fn summary(&self, num_ctx: usize) -> String {
+        format!("context: ~{}/{} tokens ({}%)", self.total(), num_ctx, percent(self.total(), num_ctx))
+    }
+
+    /// Full breakdown, including where pruning (`max_bytes`, 0 for never) starts dropping history
+    pub fn report(&self, num_ctx: usize, max_bytes: usize) -> String {
+        let mut lines = vec![
+            format!("Context: ~{} of {} tokens ({}%)", self.total(), num_ctx, percent(self.total(), num_ctx)),
+            format!("  system prompt: ~{}", self.system),
+            format!("  history: ~{}", self.history),
+            format!("  tool results: ~{}", self.tool_results),
+        ];
+        if max_bytes == 0 {
+            lines.push("  pruning: off".to_string());
+        } else {
+            lines.push(format!("  pruning starts at ~{} tokens", max_bytes / BYTES_PER_TOKEN));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Estimate how many tokens `messages` take, using the same sizes `prune` works with
+pub fn usage(messages: &[Message]) -> ContextUsage {
+    let mut usage = ContextUsage::default();
+    for message in messages {
+        let tokens = size(message).div_ceil(BYTES_PER_TOKEN);
+        match message.role.as_str() {
+            "system" => usage.system += tokens,
+            "tool" => usage.tool_results += tokens,
+            _ => usage.history += tokens,
+        }
+    }
+    usage
+}
+
+fn percent(part: usize, whole: usize) -> usize {
+    (part * 100).checked_div(whole).unwrap_or(0)
+}
+
+/// Split `range` into units that must be kept or dropped together
+fn groups(messages: &[Message], range: Range<usize>) -> Vec<Range<usize>> {
+    let mut groups = Vec::new();
+    let mut index = range.start;
+    while index < range.end {
+        let mut end = index + 1;
+        // Results belong to the assistant message whose tool calls produced them
+        while end < range.end && messages[end].role == "tool" {
+            end += 1;
+        }
+        groups.push(index..end);
+        index = end;
+    }
+    groups
+}
+
+/// Approximate prompt cost of a message in bytes
+fn size(message: &Message) -> usize {
+    let content = message.content.as_ref().map_or(0, String::len);
+    let calls = message
+        .tool_calls
+        .as_ref()
+        .map_or(0, |calls| serde_json::to_string(calls).map_or(0, |json| json.len()));
+    let images = message.images.as_ref().map_or(0, |images| images.iter().map(String::len).sum());
+    message.role.len() + content + calls + images
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FunctionCall, ToolCall};
+    use serde_json::json;
+
+    fn message(role: &str, content: &str) -> Message {
+        Message { role: role.to_string(), content: Some(content.to_string()), tool_calls: None, images: None }
+    }
+
+    fn call(name: &str) -> Message {
+        let tool_calls = vec![ToolCall { function: FunctionCall { name: name.to_string(), arguments: json!({ "path": "src/main.rs" }) } }];
+        Message { role: "assistant".to_string(), content: None, tool_calls: Some(tool_calls), images: None }
+    }
+
+    fn history() -> Vec<Message> {
+        vec![
+            message("system", "You are the Queen"),
+            message("user", "read the config"),
+            call("read_file"),
+            message("tool", &"config contents ".repeat(20)),
+            message("tool", &"more contents ".repeat(20)),
+            message("assistant", "Here is the config"),
+            message("user", "now list the workers"),
+            call("list_files"),
+            message("tool", &"worker.rs ".repeat(30)),
+            message("assistant", "There are five workers"),
+            message("user", "and the shell one?"),
+            call("read_file"),
+            message("tool", "shell.rs contents"),
+        ]
+    }
+
+    #[test]
+    fn prune_never_orphans_tool_results() {
+        let full = history();
+        let total: usize = full.iter().map(size).sum();
+        let latest = full.iter().rposition(|m| m.role == "user").unwrap();
+        let kept_tail: Vec<String> = full[latest..].iter().map(|m| format!("{:?}", m)).collect();
+
+        for budget in (1..=total).step_by(37) {
+            let mut messages = full.clone();
+            let dropped = prune(&mut messages, budget);
+            assert_eq!(messages.len() + dropped, full.len());
+
+            for index in (0..messages.len()).filter(|&i| messages[i].role == "tool") {
+                let owner = messages[..index].iter().rposition(|m| m.role != "tool").unwrap();
+                assert!(messages[owner].tool_calls.is_some(), "tool result at {} lost its call (budget {})", index, budget);
+            }
+
+            assert_eq!(messages[0].content.as_deref(), Some("You are the Queen"));
+            let tail: Vec<String> = messages[messages.len() - kept_tail.len()..].iter().map(|m| format!("{:?}", m)).collect();
+            assert_eq!(tail, kept_tail, "latest exchange changed (budget {})", budget);
+        }
+    }
+
+    #[test]
+    fn prune_keeps_everything_within_budget() {
+        let mut messages = history();
+        assert_eq!(prune(&mut messages, usize::MAX), 0);
+        assert_eq!(prune(&mut messages, 0), 0);
+        assert_eq!(messages.len(), history().len());
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex, OnceLock};
+use crate::blocking;
+
+/// Paths currently held by a mutating file operation, shared by every worker in the process
+static LOCKED: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+static RELEASED: Condvar = Condvar::new();
+
+fn locked() -> &'static Mutex<HashSet<PathBuf>> {
+    LOCKED.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Exclusive hold on one path; released on drop
+pub struct PathGuard {
+    key: PathBuf,
+}
+
+impl Drop for PathGuard {
+    fn drop(&mut self) {
+        let mut locked = locked().lock().unwrap_or_else(|e| e.into_inner());
+        locked.remove(&self.key);
+        RELEASED.notify_all();
+    }
+}
+
+/// Block until no other operation holds `path`, then hold it until the guard drops
+pub fn lock(path: &Path) -> PathGuard {
+    let key = canonical_key(path);
+    let mut locked = locked().lock().unwrap_or_else(|e| e.into_inner());
+    if locked.contains(&key) {
+        // Another write to the path can take a while, so wait without holding up the runtime
+        locked = blocking::block_in_place(|| {
+            while locked.contains(&key) {
+                locked = RELEASED.wait(locked).unwrap_or_else(|e| e.into_inner());
+            }
+            locked
+        });
+    }
+    locked.insert(key.clone());
+    PathGuard { key }
+}
+
+/// Canonicalize via the parent so files that don't exist yet still map to a stable key
+fn canonical_key(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) => fs::canonicalize(parent)
+            .map(|p| p.join(name))
+            .unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    /// Take `path` on another thread, then lock it here and check the holder had finished first
+    fn wait_for_holder(path: &Path) {
+        let (held, holding) = mpsc::channel();
+        let finished = Arc::new(AtomicBool::new(false));
+        let holder = {
+            let (path, finished) = (path.to_path_buf(), finished.clone());
+            thread::spawn(move || {
+                let _guard = lock(&path);
+                held.send(()).unwrap();
+                thread::sleep(Duration::from_millis(100));
+                finished.store(true, Ordering::SeqCst);
+            })
+        };
+
+        holding.recv().unwrap();
+        let _guard = lock(path);
+        assert!(finished.load(Ordering::SeqCst));
+        holder.join().unwrap();
+    }
+
+    #[test]
+    fn a_second_writer_waits_for_the_first() {
+        wait_for_holder(&std::env::temp_dir().join(format!("hive-lock-{}", uuid::Uuid::new_v4())));
+    }
+
+    #[tokio::test]
+    async fn waiting_works_on_a_current_thread_runtime() {
+        wait_for_holder(&std::env::temp_dir().join(format!("hive-lock-{}", uuid::Uuid::new_v4())));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn waiting_works_on_a_multi_thread_runtime() {
+        wait_for_holder(&std::env::temp_dir().join(format!("hive-lock-{}", uuid::Uuid::new_v4())));
+    }
+}
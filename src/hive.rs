@@ -0,0 +1,24 @@
+use crate::queen::Queen;
+use crate::traits::Worker;
+
+/// Library entry point: collects runtime-registered workers and builds the Queen
+#[derive(Default)]
+pub struct Hive {
+    workers: Vec<Box<dyn Worker + Send + Sync>>,
+}
+
+impl Hive {
+    pub fn new() -> Self {
+        Hive::default()
+    }
+
+    /// Add a worker alongside the inventory-discovered ones; it overrides any worker with the same role
+    pub fn register_worker(&mut self, worker: Box<dyn Worker + Send + Sync>) -> &mut Self {
+        self.workers.push(worker);
+        self
+    }
+
+    pub fn build(self) -> Queen {
+        Queen::with_workers(self.workers)
+    }
+}
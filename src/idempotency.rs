@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Mutex, OnceLock};
+
+/// Results of side-effecting tool calls per unfinished delegation, keyed by `operation_key`
+static COMPLETED: OnceLock<Mutex<HashMap<String, HashMap<u64, String>>>> = OnceLock::new();
+
+fn completed_ops() -> &'static Mutex<HashMap<String, HashMap<u64, String>>> {
+    COMPLETED.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Key for a delegation; re-sending the same instruction to the same worker yields the same key
+pub fn delegation_key(worker: &str, instruction: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    (worker, instruction).hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Key for the `sequence`-th side-effecting call in a delegation, so a retry lines up with the original run
+pub fn operation_key(sequence: usize, tool: &str, args: &serde_json::Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (sequence, tool, args.to_string()).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Result of an operation an earlier, unfinished attempt at `delegation` already applied
+pub fn completed(delegation: &str, key: u64) -> Option<String> {
+    completed_ops().lock().ok()?.get(delegation)?.get(&key).cloned()
+}
+
+/// Remember a result; failures aren't recorded so a retry gets to try them again
+pub fn record(delegation: &str, key: u64, result: &str) {
+    if result.starts_with("Error") {
+        return;
+    }
+    if let Ok(mut map) = completed_ops().lock() {
+        map.entry(delegation.to_string()).or_default().insert(key, result.to_string());
+    }
+}
+
+/// The delegation ran to completion, so sending it again is a new request rather than a retry
+pub fn finish(delegation: &str) {
+    if let Ok(mut map) = completed_ops().lock() {
+        map.remove(delegation);
+    }
+}
+
+/// Forget everything; called at the start of each user turn, since retries don't span turns
+pub fn clear() {
+    if let Ok(mut map) = completed_ops().lock() {
+        map.clear();
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::{blocking, config};
+use crate::traits::WorkerFactory;
+use crate::workspace::WorkspaceRoot;
+
+/// Helper binary that runs an isolated worker's tools, installed next to `hive`
+const HELPER: &str = "hive-worker";
+
+/// One tool call, sent to the helper as a line of JSON
+#[derive(Serialize, Deserialize)]
+struct Request {
+    tool: String,
+    arguments: Value,
+}
+
+/// The helper's answer to a `Request`, also one line of JSON
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Response {
+    Result(String),
+    /// The tool failed outright, as an `Err` from `execute_tool` would in-process
+    Error(String),
+}
+
+/// A running helper for one role
+struct Helper {
+    child: Child,
+    stdin: ChildStdin,
+    /// Lines the helper prints, read on a thread of its own so a call can time out
+    lines: Receiver<io::Result<String>>,
+    /// Workspace root it was started in; a helper from before a workspace change is replaced
+    root: PathBuf,
+}
+
+impl Helper {
+    fn spawn(role: &str, root: &Path) -> io::Result<Helper> {
+        let program = std::env::current_exe()?.with_file_name(format!("{}{}", HELPER, std::env::consts::EXE_SUFFIX));
+        // The helper runs on this process's settings (command-line overrides included), not whatever
+        // config files happen to sit in its working directory
+        let settings = serde_json::to_string(config::get()).map_err(io::Error::other)?;
+        let mut child = Command::new(program)
+            .arg(role)
+            .current_dir(root)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .spawn()?;
+        let (mut stdin, stdout) = match (child.stdin.take(), child.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => return Err(io::Error::other("helper pipes were not set up")),
+        };
+        if let Err(e) = writeln!(stdin, "{}", settings) {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        eprintln!("[ISOLATION] Started {} helper (pid {})", role, child.id());
+        Ok(Helper { child, stdin, lines, root: root.to_path_buf() })
+    }
+
+    /// Send one call and wait for its answer; `Err` describes how the helper failed
+    fn call(&mut self, tool: &str, arguments: &Value) -> std::result::Result<Response, String> {
+        let request = Request { tool: tool.to_string(), arguments: arguments.clone() };
+        let line = serde_json::to_string(&request).map_err(|e| format!("could not encode the call: {}", e))?;
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|_| format!("exited before the call was sent ({})", self.exit_status()))?;
+
+        let timeout = config::get().isolation_timeout_secs;
+        let received = if timeout == 0 {
+            self.lines.recv().map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            self.lines.recv_timeout(Duration::from_secs(timeout))
+        };
+        match received {
+            Ok(Ok(line)) => serde_json::from_str(&line).map_err(|e| format!("sent an unreadable reply ({}): {}", e, line)),
+            Ok(Err(e)) => Err(format!("could not be read from: {}", e)),
+            Err(RecvTimeoutError::Timeout) => Err(format!("took longer than {}s and was killed", timeout)),
+            Err(RecvTimeoutError::Disconnected) => Err(format!("exited ({})", self.exit_status())),
+        }
+    }
+
+    /// How the helper ended; killing first keeps a helper that closed its output but hung from blocking the wait
+    fn exit_status(&mut self) -> String {
+        let _ = self.child.kill();
+        match self.child.wait() {
+            Ok(status) => status.to_string(),
+            Err(e) => e.to_string(),
+        }
+    }
+}
+
+impl Drop for Helper {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// One slot per role, so isolated workers don't wait on each other's calls
+type Slot = Arc<Mutex<Option<Helper>>>;
+
+static HELPERS: OnceLock<Mutex<HashMap<String, Slot>>> = OnceLock::new();
+
+fn slot(role: &str) -> Slot {
+    let helpers = HELPERS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut helpers = helpers.lock().unwrap_or_else(|e| e.into_inner());
+    helpers.entry(role.to_string()).or_default().clone()
+}
+
+/// Whether `role`'s tools run in a helper process (`isolated_workers`)
+pub fn isolated(role: &str) -> bool {
+    config::get().isolated_workers.iter().any(|isolated| isolated == role)
+}
+
+/// Run a tool in `role`'s helper, starting it (again) if needed. A helper that crashes, hangs or can't be
+/// started is reported to the model as an error result; hive itself carries on
+pub fn execute(role: &str, tool: &str, arguments: &Value) -> Result<String> {
+    // The call can take up to `isolation_timeout_secs` (or forever), so other tasks must not wait behind it
+    blocking::block_in_place(|| execute_blocking(role, tool, arguments))
+}
+
+fn execute_blocking(role: &str, tool: &str, arguments: &Value) -> Result<String> {
+    let slot = slot(role);
+    let mut slot = slot.lock().unwrap_or_else(|e| e.into_inner());
+    let root = WorkspaceRoot::shared().to_path_buf();
+    let mut helper = match slot.take() {
+        Some(helper) if helper.root == root => helper,
+        _ => match Helper::spawn(role, &root) {
+            Ok(helper) => helper,
+            Err(e) => return Ok(format!("Error: could not start the {} helper for {} ({}): {}", HELPER, role, tool, e)),
+        },
+    };
+
+    match helper.call(tool, arguments) {
+        Ok(response) => {
+            *slot = Some(helper);
+            match response {
+                Response::Result(result) => Ok(result),
+                Response::Error(message) => Err(anyhow!(message)),
+            }
+        }
+        Err(failure) => {
+            eprintln!("[ISOLATION] {} helper {} while running {}", role, failure, tool);
+            Ok(format!(
+                "Error: the isolated {} worker {} while running {}; it will be restarted for the next call",
+                role, failure, tool
+            ))
+        }
+    }
+}
+
+/// Helper side: take the parent's config from the first line on stdin, then answer tool calls for `role`,
+/// one JSON request per line, until stdin closes
+pub fn serve(role: &str) -> Result<()> {
+    let mut lines = io::stdin().lock().lines();
+    let settings = lines.next().ok_or_else(|| anyhow!("stdin closed before the config arrived"))??;
+    config::init(serde_json::from_str(&settings).map_err(|e| anyhow!("unreadable config from hive: {}", e))?);
+
+    let worker = inventory::iter::<WorkerFactory>
+        .into_iter()
+        .map(|factory| (factory.0)())
+        .find(|worker| worker.role() == role)
+        .ok_or_else(|| anyhow!("no worker with role '{}' in this build", role))?;
+
+    let mut stdout = io::stdout().lock();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => match worker.run_tool(&request.tool, &request.arguments) {
+                Ok(result) => Response::Result(result),
+                Err(e) => Response::Error(e.to_string()),
+            },
+            Err(e) => Response::Error(format!("malformed request ({}): {}", e, line)),
+        };
+        writeln!(stdout, "{}", serde_json::to_string(&response)?)?;
+        stdout.flush()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+use serde_json::Value;
+
+/// Parse JSON a model produced, tolerating surrounding prose and ``` fences
+pub fn parse_lenient(text: &str) -> Result<Value, serde_json::Error> {
+    let trimmed = strip_fences(text.trim());
+    match serde_json::from_str(trimmed) {
+        Ok(value) => Ok(value),
+        Err(e) => extract_span(trimmed)
+            .and_then(|span| serde_json::from_str(span).ok())
+            .ok_or(e),
+    }
+}
+
+/// Unwrap a single ```lang ... ``` block if the text is one
+pub(crate) fn strip_fences(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    let body = rest.split_once('\n').map(|(_, body)| body).unwrap_or(rest);
+    body.trim_end().strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// The outermost `{...}` or `[...]` in the text, for answers wrapped in prose
+fn extract_span(text: &str) -> Option<&str> {
+    let start = text.find(['{', '['])?;
+    let close = if text[start..].starts_with('{') { '}' } else { ']' };
+    let end = text.rfind(close)?;
+    (end > start).then(|| &text[start..=end])
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+pub mod budget;
+mod blocking;
+mod cache;
+pub mod config;
+pub mod context;
+pub mod file_lock;
+mod hive;
+pub mod idempotency;
+pub mod isolation;
+pub mod json;
+pub mod output;
+pub mod traits;
+pub mod queen;
+pub mod request_id;
+mod scheduler;
+pub mod scratch;
+pub mod session;
+pub mod shutdown;
+pub mod undo;
+pub mod workers;
+pub mod workspace;
+
+pub use hive::Hive;
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct Message {
+    pub role: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_calls: Option<Vec<ToolCall>>,
+    /// Base64-encoded images for vision models
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub images: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct ToolCall {
+    pub function: FunctionCall,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct FunctionCall {
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
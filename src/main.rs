@@ -1,47 +1,114 @@
+use std::fs::OpenOptions;
 use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
 use anyhow::Result;
-use serde::{Deserialize, Serialize};
-
-mod traits;
-mod queen;
-mod workers;
-
-use queen::*;
-use traits::Agent;
-
-#[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct Message {
-    pub role: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub content: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_calls: Option<Vec<ToolCall>>,
-}
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use hive::config::{self, HiveConfig};
+use hive::queen::*;
+use hive::traits::Agent;
+use hive::session::{self, SessionFile};
+use hive::workspace::WorkspaceRoot;
+use hive::{context, scratch, shutdown, undo, Message};
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct ToolCall {
-    pub function: FunctionCall,
-}
+mod cli;
+
+use cli::CliArgs;
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
-pub struct FunctionCall {
-    pub name: String,
-    pub arguments: serde_json::Value,
-}
 #[tokio::main]
 async fn main() -> Result<()> {
-    let queen = Queen::new();
-    let mut messages = vec![Message {
+    let args = CliArgs::parse()?;
+    let mut config = HiveConfig::load();
+    args.apply(&mut config);
+    config::init(config);
+    // Easy to forget about, and every answer depends on it
+    if let Some(warning) = config::get().override_warning() {
+        eprintln!("\n*** {} ***\n", warning);
+    }
+
+    // Ctrl-C lets in-flight work finish instead of abandoning it mid-write; a second press exits at once
+    tokio::spawn(async {
+        if tokio::signal::ctrl_c().await.is_err() {
+            return;
+        }
+        tokio::spawn(async {
+            if tokio::signal::ctrl_c().await.is_ok() {
+                eprintln!("\nForced exit");
+                std::process::exit(130);
+            }
+        });
+        // Pressed while `quit` is already draining
+        if !shutdown::request() {
+            eprintln!("\nForced exit");
+            std::process::exit(130);
+        }
+        println!("\nShutting down (Ctrl-C again to force)");
+        drain().await;
+        scratch::cleanup();
+        std::process::exit(130);
+    });
+
+    let mut queen = Queen::new();
+    if !args.quiet {
+        println!("{}\n", queen.capabilities_report());
+    }
+    for warning in queen.check_models().await {
+        println!("Warning: {}", warning);
+    }
+    if config::get().warm_up {
+        match queen.warm_up().await {
+            Ok(elapsed) => println!("Loaded {} in {:.1}s", queen.effective_model(), elapsed.as_secs_f64()),
+            Err(e) => eprintln!("Failed to preload {}: {}", queen.effective_model(), e),
+        }
+    }
+    let ran = queen.load_startup_context();
+    if ran > 0 {
+        println!("Loaded startup context from {} command(s)", ran);
+    }
+    let prefetched = queen.prefetch_files().await;
+    if prefetched > 0 {
+        println!("Prefetched {} key file(s)", prefetched);
+    }
+    let system = Message {
         role: "system".to_string(),
-        content: Some(queen.system_prompt().to_string()),
+        content: Some(queen.build_system_prompt()),
         tool_calls: None,
-    }];
+        images: None,
+    };
+    let SessionFile { created, mut messages, .. } = match &args.session {
+        Some(path) if path.exists() => {
+            let mut saved = session::load(path)?;
+            // Always run with this build's prompt rather than the one saved with the session
+            match saved.messages.first_mut() {
+                Some(first) if first.role == "system" => *first = system,
+                _ => saved.messages.insert(0, system),
+            }
+            println!("Resumed session {} ({} messages)", path.display(), saved.messages.len());
+            saved
+        }
+        _ => SessionFile::new(vec![system]),
+    };
+    // Images attached with /image, sent along with the next prompt
+    let mut pending_images = Vec::new();
     println!("Queen is ready. Type 'quit' to exit.\n");
 
     loop {
         let input = wait_for_user_input()?;
 
         if input.eq_ignore_ascii_case("quit") {
+            if args.summary && messages.iter().any(|m| m.role == "user") {
+                match queen.summarize_session(&messages).await {
+                    Ok(summary) => println!("\nSession summary:\n{}\n", summary),
+                    Err(e) => eprintln!("Failed to summarize session: {}", e),
+                }
+            }
+            if let Some(stats) = queen.stats_report() {
+                println!("Worker stats:\n{}\n", stats);
+            }
+            shutdown::request();
+            drain().await;
+            scratch::cleanup();
             println!("Goodbye!");
             break;
         }
@@ -50,19 +117,146 @@ async fn main() -> Result<()> {
             continue;
         }
 
+        if input.starts_with('/') {
+            handle_command(&input, &mut queen, &mut messages, &mut pending_images);
+            continue;
+        }
+
         // Add user message
         messages.push(Message {
             role: "user".to_string(),
             content: Some(input),
             tool_calls: None,
+            images: (!pending_images.is_empty()).then(|| std::mem::take(&mut pending_images)),
         });
 
         // Agentic loop: keep processing until we get a final response
         let final_response = queen.run_agentic_loop(&mut messages).await?;
 
         println!("\nQueen: {}\n", final_response);
+        if config::get().report_context {
+            println!("[{}]\n", context::usage(&messages).summary(config::get().num_ctx));
+        }
+
+        if let Some(path) = &args.dump_messages
+            && let Err(e) = dump_messages(&messages, path)
+        {
+            eprintln!("Failed to dump messages to {}: {}", path.display(), e);
+        }
+        if let Some(path) = &args.delegation_trace
+            && let Err(e) = append_trace(&queen.last_trace(), path)
+        {
+            eprintln!("Failed to write delegation trace to {}: {}", path.display(), e);
+        }
+        if let Some(path) = &args.session
+            && let Err(e) = session::save(path, created, &messages)
+        {
+            eprintln!("Failed to save session to {}: {}", path.display(), e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Run a `/command` typed at the prompt instead of sending it to the Queen
+fn handle_command(input: &str, queen: &mut Queen, messages: &mut [Message], pending_images: &mut Vec<String>) {
+    let (command, rest) = input.split_once(' ').unwrap_or((input, ""));
+    let rest = rest.trim();
+    match command {
+        "/trace" if rest.is_empty() => match serde_json::to_string_pretty(messages) {
+            Ok(json) => println!("{}\n", json),
+            Err(e) => println!("Trace failed: {}\n", e),
+        },
+        "/trace" => match dump_messages(messages, Path::new(rest)) {
+            Ok(()) => println!("Wrote {} messages to {}\n", messages.len(), rest),
+            Err(e) => println!("Trace failed: {}\n", e),
+        },
+        "/context" => {
+            let config = config::get();
+            println!("{}\n", context::usage(messages).report(config.num_ctx, config.max_context_bytes));
+        }
+        "/config" => println!("{}\n", config::get().report()),
+        "/stats" => match queen.stats_report() {
+            Some(stats) => println!("{}\n", stats),
+            None => println!("No delegations yet\n"),
+        },
+        "/undo" => {
+            let write_root = config::get().write_root.as_deref().unwrap_or(Path::new(""));
+            match undo::undo_last(&WorkspaceRoot::shared().join(write_root)) {
+                Ok(message) => println!("{}\n", message),
+                Err(e) => println!("Undo failed: {}\n", e),
+            }
+        }
+        "/image" if rest.is_empty() => println!("Usage: /image <path>\n"),
+        "/image" => match std::fs::read(rest) {
+            Ok(bytes) => {
+                pending_images.push(BASE64.encode(bytes));
+                println!("Attached {} to your next message ({} image(s) pending)\n", rest, pending_images.len());
+            }
+            Err(e) => println!("Failed to read image {}: {}\n", rest, e),
+        },
+        "/persona" if rest.is_empty() => match queen.persona() {
+            Some(persona) => println!("Persona: {}\n", persona),
+            None => println!("No persona set (use /persona <text>, or /persona off to clear)\n"),
+        },
+        "/persona" => {
+            let persona = (rest != "off").then(|| rest.to_string());
+            queen.set_persona(persona);
+            // The Queen reads its style from the system prompt, so swap in the rebuilt one
+            if let Some(system) = messages.first_mut().filter(|m| m.role == "system") {
+                system.content = Some(queen.build_system_prompt());
+            }
+            match queen.persona() {
+                Some(persona) => println!("Persona set: {}\n", persona),
+                None => println!("Persona cleared\n"),
+            }
+        }
+        "/delegation" if rest.is_empty() => println!("Delegation: {} (use /delegation on, off or auto)\n", queen.delegation()),
+        "/delegation" => match rest.parse() {
+            Ok(mode) => {
+                queen.set_delegation(mode);
+                if let Some(system) = messages.first_mut().filter(|m| m.role == "system") {
+                    system.content = Some(queen.build_system_prompt());
+                }
+                println!("Delegation: {}\n", queen.delegation());
+            }
+            Err(e) => println!("{}\n", e),
+        },
+        "/cd" if rest.is_empty() => println!("Workspace: {}\n", WorkspaceRoot::shared().display()),
+        "/cd" => match queen.change_workspace(Path::new(rest)) {
+            Ok(root) => println!("Workspace is now {}\n", root.display()),
+            Err(e) => println!("Can't change workspace to {}: {}\n", rest, e),
+        },
+        _ => println!("Unknown command: {}\n", command),
+    }
+}
+
+/// Wait (up to the shutdown timeout) for in-flight delegations and tool calls, saying what was waited on
+async fn drain() {
+    let waiting = shutdown::running();
+    if waiting.is_empty() {
+        return;
     }
+    let timeout = config::get().shutdown_timeout_secs;
+    println!("Waiting up to {}s for: {}", timeout, waiting.join("; "));
+    let abandoned = shutdown::wait(Duration::from_secs(timeout)).await;
+    if abandoned.is_empty() {
+        println!("In-flight work finished");
+    } else {
+        println!("Gave up waiting for: {}", abandoned.join("; "));
+    }
+}
+
+/// Write the exact message history sent to Ollama as pretty JSON
+fn dump_messages(messages: &[Message], path: &Path) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(messages)?)?;
+    Ok(())
+}
 
+/// Append one turn's delegation trace as a JSON line
+fn append_trace(trace: &DelegationTrace, path: &Path) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(trace)?)?;
     Ok(())
 }
 
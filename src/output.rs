@@ -0,0 +1,20 @@
+use regex::Regex;
+use std::sync::OnceLock;
+use crate::json;
+
+/// `<think>`-style blocks some models emit before answering
+fn reasoning_tags() -> &'static Regex {
+    static TAGS: OnceLock<Regex> = OnceLock::new();
+    TAGS.get_or_init(|| Regex::new(r"(?s)<(think|thinking|reasoning)>.*?</(think|thinking|reasoning)>").unwrap())
+}
+
+/// Strip reasoning blocks and unwrap an answer that is nothing but one fenced block
+pub fn clean(text: &str) -> String {
+    let stripped = reasoning_tags().replace_all(text, "");
+    let trimmed = stripped.trim();
+    // Several blocks (or prose around one) carry meaning, so only a lone block is unwrapped
+    if trimmed.starts_with("```") && trimmed.ends_with("```") && trimmed.matches("```").count() == 2 {
+        return json::strip_fences(trimmed).to_string();
+    }
+    trimmed.to_string()
+}
@@ -1,12 +1,114 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use anyhow::Result;
 use reqwest::Client;
+use serde::Serialize;
 use serde_json::json;
-use crate::traits::{Agent, Worker, WorkerFactory, Tool, ToolFunction};
-use crate::Message;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use crate::traits::{
+    Agent, Escalation, RunOptions, Worker, WorkerFactory, Tool, ToolFunction, ToolStep, EMPTY_RESPONSE_NUDGE, MAX_EMPTY_RETRIES, NO_RESPONSE,
+    skipped_tool_call,
+};
+use crate::budget::{self, LoopBudget};
+use crate::workspace::WorkspaceRoot;
+use crate::config::DelegationMode;
+use crate::{cache, config, context, idempotency, isolation, request_id, shutdown, Message};
 
 pub struct Queen {
-    workers: HashMap<&'static str, Box<dyn Worker + Send + Sync>>
+    workers: HashMap<&'static str, Box<dyn Worker + Send + Sync>>,
+    stats: Mutex<HashMap<&'static str, WorkerStats>>,
+    /// Delegations made during the current (or most recent) user turn
+    trace: Mutex<DelegationTrace>,
+    /// Roles registered at runtime rather than through inventory
+    custom_roles: HashSet<&'static str>,
+    /// Workers whose model isn't pulled, with the message to return instead of delegating
+    degraded: HashMap<&'static str, String>,
+    /// Style instructions for answers to the user, appended to the system prompt
+    persona: Option<String>,
+    /// Recent worker results, kept so the `recall` tool can return them after pruning
+    recall: Mutex<RecallBuffer>,
+    /// Output of `startup_context_commands`, appended to the system prompt
+    startup_context: Option<String>,
+    /// `prefetch_files` that were read at startup, with their contents, in config order
+    prefetched: Vec<(String, String)>,
+    /// Whether turns get tools; starts from the config and can be switched with `/delegation`
+    delegation: DelegationMode,
+}
+
+/// Ring buffer of the last `recall_size` worker results, numbered in the order they arrived
+#[derive(Default)]
+struct RecallBuffer {
+    next_id: usize,
+    entries: VecDeque<Recalled>,
+}
+
+struct Recalled {
+    id: usize,
+    worker: String,
+    instruction: String,
+    content: String,
+    hash: String,
+}
+
+impl RecallBuffer {
+    fn push(&mut self, worker: &str, instruction: &str, content: &str, capacity: usize) {
+        if capacity == 0 {
+            return;
+        }
+        self.next_id += 1;
+        let mut hasher = DefaultHasher::new();
+        content.hash(&mut hasher);
+        self.entries.push_back(Recalled {
+            id: self.next_id,
+            worker: worker.to_string(),
+            instruction: instruction.to_string(),
+            content: content.to_string(),
+            hash: format!("{:08x}", hasher.finish() as u32),
+        });
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Most recent entry whose hash starts with `query`, or whose worker or instruction mentions it
+    fn find(&self, query: &str) -> Option<&Recalled> {
+        let query = query.trim().to_lowercase();
+        self.entries.iter().rev().find(|entry| {
+            entry.hash.starts_with(&query)
+                || entry.worker.to_lowercase() == query
+                || entry.instruction.to_lowercase().contains(&query)
+        })
+    }
+}
+
+/// What the Queen did with one user turn, for evaluating its delegation choices
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct DelegationTrace {
+    pub user_input: String,
+    pub delegations: Vec<Delegation>,
+    pub final_response: String,
+    pub iterations: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Delegation {
+    pub worker: String,
+    pub instruction: String,
+    pub ok: bool,
+}
+
+/// Session totals for one worker's delegations
+#[derive(Default)]
+struct WorkerStats {
+    calls: u32,
+    successes: u32,
+    total: Duration,
 }
 
 impl Agent for Queen {
@@ -19,13 +121,38 @@ impl Agent for Queen {
     fn system_prompt(&self) -> &'static str {
         system_prompt()
     }
+    // The Queen's prompt carries its own decision framework
+    fn append_reasoning_instructions(&self) -> bool {
+        false
+    }
+    fn config_name(&self) -> &'static str {
+        "queen"
+    }
+    // Pinning keeps the model resident even when workers share its GPU
+    fn keep_alive(&self) -> Option<String> {
+        if config::get().pin_queen_model {
+            return Some("-1".to_string());
+        }
+        config::get().keep_alive_for(self.effective_model()).map(str::to_string)
+    }
     fn client(&self) -> Client {
         Client::new()
     }
 }
 
+impl Default for Queen {
+    fn default() -> Self {
+        Queen::new()
+    }
+}
+
 impl Queen {
     pub fn new() -> Queen {
+        Queen::with_workers(Vec::new())
+    }
+
+    /// Build from inventory-registered workers plus `extra`; on a role collision `extra` wins
+    pub fn with_workers(extra: Vec<Box<dyn Worker + Send + Sync>>) -> Queen {
         let workers = inventory::iter::<WorkerFactory>
             .into_iter()
             .map(|factory| {
@@ -34,23 +161,256 @@ impl Queen {
             })
             .collect();
 
-        Queen { workers }
+        let mut queen = Queen {
+            workers,
+            stats: Mutex::new(HashMap::new()),
+            trace: Mutex::new(DelegationTrace::default()),
+            custom_roles: HashSet::new(),
+            degraded: HashMap::new(),
+            persona: config::get().persona.clone(),
+            recall: Mutex::new(RecallBuffer::default()),
+            startup_context: None,
+            prefetched: Vec::new(),
+            delegation: config::get().delegation,
+        };
+        for worker in extra {
+            queen.add_worker(worker);
+        }
+        queen
+    }
+
+    /// Register a worker at runtime, replacing any existing worker with the same role
+    pub fn add_worker(&mut self, worker: Box<dyn Worker + Send + Sync>) {
+        let role = worker.role();
+        self.custom_roles.insert(role);
+        if self.workers.insert(role, worker).is_some() {
+            eprintln!("[QUEEN] Warning: worker '{}' was replaced by a runtime registration", role);
+        }
+    }
+
+    /// Move the session to `path` and rebuild the inventory workers so they sandbox to it
+    /// Runtime-registered workers are kept as they are, since they can't be rebuilt
+    pub fn change_workspace(&mut self, path: &Path) -> io::Result<WorkspaceRoot> {
+        let root = WorkspaceRoot::set_shared(path)?;
+        for factory in inventory::iter::<WorkerFactory> {
+            let worker = (factory.0)();
+            if !self.custom_roles.contains(worker.role()) {
+                self.workers.insert(worker.role(), worker);
+            }
+        }
+        Ok(root)
+    }
+
+    /// Ask each Ollama endpoint which models are pulled and mark workers whose model (and every
+    /// fallback) is missing as degraded. Returns operator-facing warnings
+    pub async fn check_models(&mut self) -> Vec<String> {
+        let mut urls = vec![self.resolve_url()];
+        urls.extend(config::get().backends.iter().map(|b| b.url.clone()));
+        urls.sort_unstable();
+        urls.dedup();
+
+        let mut available = HashSet::new();
+        for url in &urls {
+            let tags_url = url.replace("/api/chat", "/api/tags");
+            let mut request = self.client().get(&tags_url);
+            for (name, value) in self.headers() {
+                request = request.header(name, value);
+            }
+            // A failed listing says nothing about which models are pulled, so nobody is marked degraded over it
+            let response = match request.send().await {
+                Ok(response) if response.status().is_success() => response,
+                Ok(response) => return vec![format!("Ollama at {} answered {} when asked for its models", tags_url, response.status())],
+                Err(e) => return vec![format!("Can't reach Ollama at {} to check models: {}", tags_url, e)],
+            };
+            let tags: serde_json::Value = match response.json().await {
+                Ok(tags) => tags,
+                Err(e) => return vec![format!("Can't read the model list from {}: {}", tags_url, e)],
+            };
+            let names = tags["models"].as_array().into_iter().flatten().filter_map(|m| m["name"].as_str());
+            available.extend(names.map(|name| with_tag(name).to_string()));
+        }
+        let pulled = |model: &str| available.contains(with_tag(model).as_ref());
+
+        let mut warnings = Vec::new();
+        let queen_model = self.effective_model();
+        if !pulled(queen_model) {
+            warnings.push(format!("The Queen's model {} isn't pulled; run `ollama pull {}`", queen_model, queen_model));
+        }
+        self.degraded.clear();
+        for (role, worker) in &self.workers {
+            let Some(model) = worker.model_name() else {
+                continue;
+            };
+            let model = config::get().model_override.as_deref().unwrap_or(model);
+            let fallbacks = config::get().fallback_models.get(model).cloned().unwrap_or_default();
+            if pulled(model) || fallbacks.iter().any(|m| pulled(m)) {
+                continue;
+            }
+            warnings.push(format!("Worker '{}' needs {}, which isn't pulled; run `ollama pull {}`", role, model, model));
+            self.degraded.insert(
+                role,
+                format!("Error: worker '{}' is unavailable because its model {} isn't pulled; use another worker", role, model),
+            );
+        }
+        warnings
+    }
+
+    /// Load the model with an empty chat request so the first real turn doesn't pay for it
+    pub async fn warm_up(&self) -> Result<Duration> {
+        let started = Instant::now();
+        self.make_request(&[], None).await?;
+        Ok(started.elapsed())
+    }
+
+    /// System prompt with the `{worker_list}` placeholder filled in
+    pub fn build_system_prompt(&self) -> String {
+        let mut prompt = Agent::build_system_prompt(self).replace("{worker_list}", &self.get_worker_list());
+        if config::get().clarify_first {
+            prompt.push_str(CLARIFY_DIRECTIVE);
+        }
+        if let Some(persona) = &self.persona {
+            prompt.push_str(&format!("\n\n# Response Style\nWhen answering the user: {}", persona));
+        }
+        if self.delegation == DelegationMode::Off {
+            prompt.push_str(DIRECT_DIRECTIVE);
+        }
+        if let Some(context) = &self.startup_context {
+            prompt.push_str(&format!("\n\n# Project Context\nOutput of commands run in the workspace at startup:\n{}", context));
+        }
+        if !self.prefetched.is_empty() {
+            let files: Vec<String> = self
+                .prefetched
+                .iter()
+                .map(|(path, content)| format!("## {}\n```\n{}\n```", path, content.trim_end()))
+                .collect();
+            prompt.push_str(&format!(
+                "\n\n# Key Files\nRead at startup, so you don't need a worker to read these again:\n{}",
+                files.join("\n\n")
+            ));
+        }
+        prompt
+    }
+
+    /// Read `prefetch_files` from the workspace, `prefetch_parallelism` at a time, keeping them for the system prompt
+    /// Missing, unreadable or non-UTF-8 files are skipped; together they stay within `max_read_bytes`. Returns how many were read
+    pub async fn prefetch_files(&mut self) -> usize {
+        let config = config::get();
+        let root = WorkspaceRoot::shared();
+        let permits = Arc::new(Semaphore::new(config.prefetch_parallelism.max(1)));
+        let mut reads = JoinSet::new();
+        for (index, path) in config.prefetch_files.iter().enumerate() {
+            let full_path = root.join(path);
+            let (root, permits) = (root.clone(), permits.clone());
+            reads.spawn(async move {
+                let _permit = permits.acquire_owned().await.ok()?;
+                // Same sandbox as the workers: nothing outside the workspace, even through a symlink
+                let full_path = tokio::fs::canonicalize(&full_path).await.ok().filter(|p| p.starts_with(&*root))?;
+                Some((index, tokio::fs::read_to_string(full_path).await.ok()?))
+            });
+        }
+        let mut contents: Vec<(usize, String)> = reads.join_all().await.into_iter().flatten().collect();
+        contents.sort_by_key(|(index, _)| *index);
+
+        let mut budget = config.max_read_bytes;
+        self.prefetched.clear();
+        for (index, mut content) in contents {
+            if budget == 0 {
+                eprintln!("[QUEEN] Not prefetching {}: the {} byte read budget is used up", config.prefetch_files[index], config.max_read_bytes);
+                continue;
+            }
+            if content.len() > budget {
+                content.truncate(content.floor_char_boundary(budget));
+                content.push_str("\n[truncated]");
+                budget = 0;
+            } else {
+                budget -= content.len();
+            }
+            self.prefetched.push((config.prefetch_files[index].clone(), content));
+        }
+        self.prefetched.len()
+    }
+
+    /// Run `startup_context_commands` through the shell worker so their output grounds the system prompt
+    /// Returns how many commands ran
+    pub fn load_startup_context(&mut self) -> usize {
+        let config = config::get();
+        if config.startup_context_commands.is_empty() {
+            return 0;
+        }
+        #[cfg(feature = "shell")]
+        {
+            let timeout = Duration::from_secs(config.startup_context_timeout_secs);
+            let sections: Vec<String> = config
+                .startup_context_commands
+                .iter()
+                .map(|command| {
+                    eprintln!("[QUEEN] Running startup context command: {}", command);
+                    format!("$ {}\n```\n{}\n```", command, crate::workers::context_output(command, timeout).trim_end())
+                })
+                .collect();
+            self.startup_context = Some(sections.join("\n\n"));
+            sections.len()
+        }
+        #[cfg(not(feature = "shell"))]
+        {
+            eprintln!("[QUEEN] Warning: startup_context_commands needs the shell worker, which isn't compiled in");
+            0
+        }
+    }
+
+    pub fn delegation(&self) -> DelegationMode {
+        self.delegation
     }
 
-    /// Build the list of available workers as a formatted string
+    /// Switch delegation on, off or to per-turn; takes effect once the system prompt is rebuilt
+    pub fn set_delegation(&mut self, mode: DelegationMode) {
+        self.delegation = mode;
+    }
+
+    pub fn persona(&self) -> Option<&str> {
+        self.persona.as_deref()
+    }
+
+    /// Change how answers to the user are styled; takes effect once the system prompt is rebuilt
+    pub fn set_persona(&mut self, persona: Option<String>) {
+        self.persona = persona.filter(|p| !p.trim().is_empty());
+    }
+
+    /// Build the list of available workers, and the tools each one has, as a formatted string
     fn get_worker_list(&self) -> String {
         self.workers
             .values()
-            .map(|w| format!("- **{}**: {}", w.role(), w.description()))
+            .map(|w| {
+                let tools: Vec<String> = w.get_tools().into_iter().map(|t| t.function.name).collect();
+                if self.degraded.contains_key(w.role()) {
+                    format!("- **{}** (unavailable: model not pulled): {}", w.role(), w.description())
+                } else if tools.is_empty() {
+                    format!("- **{}**: {}", w.role(), w.description())
+                } else {
+                    format!("- **{}**: {}\n  Tools: {}", w.role(), w.description(), tools.join(", "))
+                }
+            })
             .collect::<Vec<_>>()
             .join("\n")
     }
 
-    /// Build the delegate_to_worker tool with available worker names
+    /// Registered worker role most similar to `name`, with its similarity from 0.0 to 1.0
+    fn closest_worker(&self, name: &str) -> Option<(&'static str, f64)> {
+        let name = name.trim().to_lowercase();
+        self.workers
+            .keys()
+            .map(|role| (*role, strsim::normalized_levenshtein(&name, role)))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Build the delegate_to_worker tool with available worker names; none when delegation is off
     fn get_tools(&self) -> Vec<Tool> {
+        if self.delegation == DelegationMode::Off {
+            return Vec::new();
+        }
         let worker_names: Vec<&str> = self.workers.keys().copied().collect();
 
-        vec![Tool {
+        let mut tools = vec![Tool {
             tool_type: "function".to_string(),
             function: ToolFunction {
                 name: "delegate_to_worker".to_string(),
@@ -66,30 +426,265 @@ impl Queen {
                         "instruction": {
                             "type": "string",
                             "description": "Natural language instruction for the worker"
+                        },
+                        "max_chars": {
+                            "type": "integer",
+                            "description": "Optional cap on the length of the worker's answer"
+                        },
+                        "json_mode": {
+                            "type": "boolean",
+                            "description": "Require the worker to answer with valid JSON"
+                        },
+                        "include_trace": {
+                            "type": "boolean",
+                            "description": "Also return the tool calls the worker made and their results, to check how it reached its answer"
                         }
                     },
                     "required": ["worker", "instruction"]
                 }),
             },
-        }]
+        }];
+
+        if config::get().recall_size > 0 {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "recall".to_string(),
+                    description: "Get back an earlier worker result, even one no longer in the conversation. \
+                        With no arguments, lists the results that can be recalled".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "id": {
+                                "type": "integer",
+                                "description": "Number of the result, as shown in the listing"
+                            },
+                            "query": {
+                                "type": "string",
+                                "description": "A result's hash, its worker's name, or words from the instruction that produced it"
+                            }
+                        },
+                        "required": []
+                    }),
+                },
+            });
+        }
+
+        tools.push(Tool {
+            tool_type: "function".to_string(),
+            function: ToolFunction {
+                name: "calculate".to_string(),
+                description: "Evaluate an arithmetic expression exactly (+ - * / % ^ and parentheses). \
+                    Use it for any calculation instead of working the numbers out yourself".to_string(),
+                parameters: json!({
+                    "type": "object",
+                    "properties": {
+                        "expression": {
+                            "type": "string",
+                            "description": "The expression, e.g. (1250 * 12) / 7"
+                        }
+                    },
+                    "required": ["expression"]
+                }),
+            },
+        });
+
+        if config::get().clarify_first {
+            tools.push(Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "ask_user".to_string(),
+                    description: "Ask the user a clarifying question and wait for their answer".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "question": {
+                                "type": "string",
+                                "description": "The question to ask the user"
+                            }
+                        },
+                        "required": ["question"]
+                    }),
+                },
+            });
+        }
+
+        tools
     }
 
     /// Execute a tool call and return the result
-    async fn execute_tool_call(&self, name: &str, arguments: &serde_json::Value) -> Result<String> {
+    async fn execute_tool_call(&self, name: &str, arguments: &serde_json::Value, budget: &Arc<LoopBudget>) -> Result<String> {
         match name {
             "delegate_to_worker" => {
-                let worker_name = arguments["worker"].as_str().unwrap_or("");
+                let mut worker_name = arguments["worker"].as_str().unwrap_or("");
+                // Small models often misspell roles; fix the obvious cases instead of burning an iteration
+                if !self.workers.contains_key(worker_name)
+                    && let Some((role, similarity)) = self.closest_worker(worker_name)
+                    && similarity >= AUTO_CORRECT_SIMILARITY
+                {
+                    eprintln!("[QUEEN] Worker '{}' not found, using '{}' instead", worker_name, role);
+                    worker_name = role;
+                }
                 let instruction = arguments["instruction"].as_str().unwrap_or("");
+                let delegation_key = idempotency::delegation_key(worker_name, instruction);
+                let include_trace = arguments["include_trace"].as_bool().unwrap_or(false);
+                let analysis = self.analysis_inputs(worker_name, instruction);
+                let verify_writes = config::get().verify_writes;
+                // The cache and write verification need the steps too, to see what the worker changed
+                let trace = (include_trace || analysis.is_some() || verify_writes).then(|| Arc::new(Mutex::new(Vec::new())));
+                let options = RunOptions {
+                    max_chars: arguments["max_chars"].as_u64().map(|n| n as usize),
+                    budget: Some(budget.clone()),
+                    json_mode: arguments["json_mode"].as_bool().unwrap_or(false),
+                    idempotency_key: Some(delegation_key.clone()),
+                    trace: trace.clone(),
+                    ..RunOptions::default()
+                };
 
-                eprintln!("[QUEEN] Delegating to worker '{}' with instruction: {}", worker_name, instruction);
+                let id = request_id::new_id();
+                eprintln!("[QUEEN req={}] Delegating to worker '{}' with instruction: {}", id, worker_name, instruction);
 
+                if shutdown::requested() {
+                    eprintln!("[QUEEN req={}] Not delegating to '{}': hive is shutting down", id, worker_name);
+                    return Ok(shutdown::refused_tool_call("delegate_to_worker"));
+                }
+                if let Some(message) = self.degraded.get(worker_name) {
+                    eprintln!("[QUEEN req={}] Worker '{}' is degraded, not delegating", id, worker_name);
+                    self.record_delegation(worker_name, instruction, false);
+                    return Ok(message.clone());
+                }
+                let limit = config::get().max_similar_delegations;
+                if limit > 0 && self.similar_delegations(worker_name, instruction) >= limit {
+                    eprintln!("[QUEEN req={}] Delegation cycle: '{}' was already given this instruction {} times", id, worker_name, limit);
+                    self.record_delegation(worker_name, instruction, false);
+                    return Ok(format!(
+                        "Error: '{}' has already been given nearly this instruction {} times this turn without settling it. \
+                         Stop re-delegating it: take a different approach, or answer the user directly with what you have.",
+                        worker_name, limit
+                    ));
+                }
+                if let Some((key, inputs)) = analysis
+                    && let Some(cached) = cache::get_analysis(key, inputs)
+                {
+                    eprintln!("[QUEEN req={}] Reusing '{}' answer; the files it read are unchanged", id, worker_name);
+                    self.record_delegation(worker_name, instruction, true);
+                    return Ok(cached);
+                }
                 if let Some(worker) = self.workers.get(worker_name) {
-                    let result = worker.process(instruction).await;
-                    eprintln!("[QUEEN] Worker '{}' returned: {:?}", worker_name, result);
+                    let _running = shutdown::begin(format!("delegation to {}: {}", worker_name, preview(instruction, RECALL_LISTING_CHARS)));
+                    if config::get().show_work {
+                        println!("\n[{}] {}", worker_name, instruction);
+                    }
+                    let started = Instant::now();
+                    let (progress, mut updates) = mpsc::unbounded_channel();
+                    let report_progress = async {
+                        while let Some(update) = updates.recv().await {
+                            eprintln!("[QUEEN req={}] Worker '{}' progress: {}", id, worker_name, update);
+                        }
+                    };
+                    // The channel closes when the worker finishes, which ends the reporter
+                    let delegation = async {
+                        tokio::join!(
+                            request_id::scope(id.clone(), worker.process_streaming(instruction, &options, progress)),
+                            report_progress
+                        )
+                        .0
+                    };
+                    let timeout_secs = config::get().delegation_timeout_secs;
+                    let deadline = (timeout_secs > 0).then(|| Instant::now() + Duration::from_secs(timeout_secs));
+                    let delegation = budget::with_deadline(deadline, delegation);
+                    let result = if timeout_secs == 0 {
+                        delegation.await
+                    } else {
+                        match tokio::time::timeout(Duration::from_secs(timeout_secs), delegation).await {
+                            Ok(result) => result,
+                            Err(_) => {
+                                eprintln!("[QUEEN req={}] Worker '{}' timed out after {}s, abandoning delegation", id, worker_name, timeout_secs);
+                                Ok(format!(
+                                    "Error: worker '{}' did not finish within {}s and was cancelled; try a smaller instruction or another approach",
+                                    worker_name, timeout_secs
+                                ))
+                            }
+                        }
+                    };
+                    let escalation = result.as_ref().ok().and_then(|text| Escalation::parse(text));
+                    let mut ok = escalation.is_none() && matches!(&result, Ok(text) if !text.starts_with("Error"));
+                    let steps = trace
+                        .map(|trace| std::mem::take(&mut *trace.lock().unwrap_or_else(|e| e.into_inner())))
+                        .unwrap_or_default();
+                    // Small models sometimes report a write they never made
+                    let result = match result {
+                        Ok(text) if ok && verify_writes => {
+                            let missing = missing_writes(&WorkspaceRoot::shared().worker_base(worker_name), &text, &steps);
+                            if missing.is_empty() {
+                                Ok(text)
+                            } else {
+                                eprintln!("[QUEEN req={}] Worker '{}' reported writing missing files: {}", id, worker_name, missing.join(", "));
+                                ok = false;
+                                Ok(format!(
+                                    "{}\n\n[write check] The worker reported writing these, but they don't exist: {}. \
+                                     Don't tell the user they were written; have them created first.",
+                                    text,
+                                    missing.join(", ")
+                                ))
+                            }
+                        }
+                        result => result,
+                    };
+                    // An answer is only reusable if producing it changed nothing
+                    if ok
+                        && let Some((key, inputs)) = analysis
+                        && let Ok(text) = &result
+                        && !steps.iter().any(|step| step.side_effects)
+                    {
+                        cache::insert_analysis(key, inputs, text.clone());
+                    }
+                    let result = match result {
+                        Ok(text) if include_trace => Ok(with_steps(text, &steps)),
+                        result => result,
+                    };
+                    self.record_stats(worker.role(), started.elapsed(), ok);
+                    self.record_delegation(worker_name, instruction, ok);
+                    // Only a cut-short delegation can be retried into repeating its side effects
+                    if ok {
+                        idempotency::finish(&delegation_key);
+                    }
+                    eprintln!("[QUEEN req={}] Worker '{}' returned: {:?}", id, worker_name, result);
+                    if config::get().show_work {
+                        match &result {
+                            Ok(text) => println!("[{} result] {}\n", worker_name, preview(text, SHOW_WORK_CHARS)),
+                            Err(e) => println!("[{} failed] {}\n", worker_name, e),
+                        }
+                    }
+                    if let Some(escalation) = escalation {
+                        return self.reroute(worker_name, escalation, arguments, budget).await;
+                    }
                     result
                 } else {
-                    eprintln!("[QUEEN] Error: Worker '{}' not found", worker_name);
-                    Ok(format!("Error: Worker '{}' not found", worker_name))
+                    eprintln!("[QUEEN req={}] Error: Worker '{}' not found", id, worker_name);
+                    self.record_delegation(worker_name, instruction, false);
+                    match self.closest_worker(worker_name) {
+                        Some((role, similarity)) if similarity >= SUGGEST_SIMILARITY => {
+                            Ok(format!("Error: Worker '{}' not found. Did you mean '{}'?", worker_name, role))
+                        }
+                        _ => Ok(format!(
+                            "Error: Worker '{}' not found. Available workers: {}",
+                            worker_name,
+                            self.workers.keys().copied().collect::<Vec<_>>().join(", ")
+                        )),
+                    }
+                }
+            }
+            "recall" => Ok(self.recall(arguments["id"].as_u64(), arguments["query"].as_str())),
+            "calculate" => Ok(calculate(arguments["expression"].as_str().unwrap_or(""))),
+            "ask_user" => {
+                let question = arguments["question"].as_str().unwrap_or("");
+                eprintln!("[QUEEN] Asking user: {}", question);
+                let answer = ask_user(question).await?;
+                if answer.is_empty() {
+                    Ok("The user gave no answer; proceed with your best judgement.".to_string())
+                } else {
+                    Ok(answer)
                 }
             }
             _ => {
@@ -99,55 +694,595 @@ impl Queen {
         }
     }
 
+    /// Cache key and input hash for a delegation whose answer can be reused: the worker is listed in
+    /// `analysis_cache` and the instruction names at least one existing file in its directory
+    fn analysis_inputs(&self, worker: &str, instruction: &str) -> Option<(u64, u64)> {
+        if !config::get().analysis_cache.iter().any(|role| role == worker) {
+            return None;
+        }
+        let files = referenced_files(&WorkspaceRoot::shared().worker_base(worker), instruction);
+        if files.is_empty() {
+            return None;
+        }
+        Some((cache::analysis_key(worker, instruction), cache::hash_files(&files)?))
+    }
+
+    /// Hand a task a worker escalated to the worker it suggested, at most once per delegation
+    /// Otherwise tell the model why the worker gave up so it can pick another route itself
+    async fn reroute(&self, from: &str, escalation: Escalation, arguments: &serde_json::Value, budget: &Arc<LoopBudget>) -> Result<String> {
+        let target = escalation
+            .suggested_worker
+            .as_deref()
+            .and_then(|name| self.closest_worker(name))
+            .filter(|&(role, similarity)| similarity >= AUTO_CORRECT_SIMILARITY && role != from && !self.degraded.contains_key(role))
+            .map(|(role, _)| role);
+        match target {
+            Some(role) if !arguments["escalated_from"].is_string() => {
+                eprintln!("[QUEEN] Worker '{}' escalated ({}), re-delegating to '{}'", from, escalation.reason, role);
+                let mut rerouted = arguments.clone();
+                rerouted["worker"] = json!(role);
+                rerouted["escalated_from"] = json!(from);
+                let result = Box::pin(self.execute_tool_call("delegate_to_worker", &rerouted, budget)).await?;
+                Ok(format!("Worker '{}' handed this to '{}' ({}):\n{}", from, role, escalation.reason, result))
+            }
+            _ => {
+                eprintln!("[QUEEN] Worker '{}' escalated ({}), returning it to the model", from, escalation.reason);
+                Ok(format!(
+                    "Error: worker '{}' couldn't do this: {}{}. Delegate it to a better-suited worker or handle it another way.",
+                    from,
+                    escalation.reason,
+                    escalation.suggested_worker.map(|w| format!(" (suggested: {})", w)).unwrap_or_default()
+                ))
+            }
+        }
+    }
+
+    /// Remember a worker's result for the `recall` tool
+    fn remember(&self, arguments: &serde_json::Value, result: &str) {
+        let worker = arguments["worker"].as_str().unwrap_or("");
+        let instruction = arguments["instruction"].as_str().unwrap_or("");
+        self.recall
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(worker, instruction, result, config::get().recall_size);
+    }
+
+    /// An earlier worker result by number or query, or a listing of what can be recalled
+    fn recall(&self, id: Option<u64>, query: Option<&str>) -> String {
+        let buffer = self.recall.lock().unwrap_or_else(|e| e.into_inner());
+        let found = match (id, query) {
+            (Some(id), _) => buffer.entries.iter().find(|entry| entry.id as u64 == id),
+            (None, Some(query)) if !query.trim().is_empty() => buffer.find(query),
+            _ => {
+                if buffer.entries.is_empty() {
+                    return "No worker results to recall yet".to_string();
+                }
+                let listing: Vec<_> = buffer
+                    .entries
+                    .iter()
+                    .map(|entry| {
+                        json!({
+                            "id": entry.id,
+                            "worker": entry.worker,
+                            "instruction": preview(&entry.instruction, RECALL_LISTING_CHARS),
+                            "hash": entry.hash,
+                            "chars": entry.content.chars().count(),
+                        })
+                    })
+                    .collect();
+                return json!(listing).to_string();
+            }
+        };
+        match found {
+            Some(entry) => format!("[#{} {}: {}]\n{}", entry.id, entry.worker, entry.instruction, entry.content),
+            None => "Error: no matching result; call recall with no arguments to list what's available".to_string(),
+        }
+    }
+
+    /// Workers, what each can touch, and where requests go, so the operator sees the security surface
+    pub fn capabilities_report(&self) -> String {
+        let config = config::get();
+        let mut roles: Vec<_> = self.workers.keys().copied().collect();
+        roles.sort_unstable();
+        let mut lines = vec![format!("Queen: {}", self.effective_model())];
+        for role in roles {
+            let worker = &self.workers[role];
+            let capabilities = worker.capabilities().names();
+            lines.push(format!(
+                "- {} ({}): {}{}",
+                role,
+                worker.model_name().map(|model| config.model_override.as_deref().unwrap_or(model)).unwrap_or("no model"),
+                if capabilities.is_empty() { "no external access".to_string() } else { capabilities.join(", ") },
+                if isolation::isolated(role) { " [isolated process]" } else { "" }
+            ));
+        }
+        for backend in &config.backends {
+            lines.push(format!("Backend {}: {} [{}]", backend.name, backend.url, backend.models.join(", ")));
+        }
+        if let Some(root) = &config.write_root {
+            lines.push(format!("Writes confined to {}", root.display()));
+        }
+        lines.join("\n")
+    }
+
+    /// Per-worker call counts, success rates and timings, slowest average first
+    pub fn stats_report(&self) -> Option<String> {
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        if stats.is_empty() {
+            return None;
+        }
+
+        let mut rows: Vec<_> = stats.iter().collect();
+        rows.sort_by_key(|(_, s)| std::cmp::Reverse(s.total / s.calls.max(1)));
+        let lines: Vec<String> = rows
+            .into_iter()
+            .map(|(role, s)| {
+                format!(
+                    "- {}: {} call(s), {:.0}% ok, total {:.1}s, avg {:.1}s",
+                    role,
+                    s.calls,
+                    100.0 * s.successes as f64 / s.calls.max(1) as f64,
+                    s.total.as_secs_f64(),
+                    s.total.as_secs_f64() / s.calls.max(1) as f64
+                )
+            })
+            .collect();
+        Some(lines.join("\n"))
+    }
+
+    fn record_stats(&self, role: &'static str, elapsed: Duration, ok: bool) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = stats.entry(role).or_default();
+        entry.calls += 1;
+        entry.total += elapsed;
+        if ok {
+            entry.successes += 1;
+        }
+    }
+
+    fn record_delegation(&self, worker: &str, instruction: &str, ok: bool) {
+        let mut trace = self.trace.lock().unwrap_or_else(|e| e.into_inner());
+        trace.delegations.push(Delegation {
+            worker: worker.to_string(),
+            instruction: instruction.to_string(),
+            ok,
+        });
+    }
+
+    /// How many of this turn's delegations gave `worker` an instruction close to `instruction`
+    /// Catches ping-pong between the Queen and a worker that exact-match idempotency keys miss
+    fn similar_delegations(&self, worker: &str, instruction: &str) -> usize {
+        let instruction = instruction.trim().to_lowercase();
+        let trace = self.trace.lock().unwrap_or_else(|e| e.into_inner());
+        trace
+            .delegations
+            .iter()
+            .filter(|d| d.worker == worker)
+            .filter(|d| strsim::normalized_levenshtein(&d.instruction.trim().to_lowercase(), &instruction) >= SIMILAR_INSTRUCTION)
+            .count()
+    }
+
+    fn finish_trace(&self, final_response: &str, iterations: usize) {
+        let mut trace = self.trace.lock().unwrap_or_else(|e| e.into_inner());
+        trace.final_response = final_response.to_string();
+        trace.iterations = iterations;
+    }
+
+    /// Delegation trace of the most recent `run_agentic_loop` call
+    pub fn last_trace(&self) -> DelegationTrace {
+        self.trace.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Have the model check its final answer (the last message) against this turn's tool results
+    /// Returns the revised answer, or `None` if the check came back empty
+    async fn verify_answer(&self, messages: &[Message]) -> Result<Option<String>> {
+        let mut check = messages.to_vec();
+        check.push(Message {
+            role: "user".to_string(),
+            content: Some(VERIFY_PROMPT.to_string()),
+            tool_calls: None,
+            images: None,
+        });
+        let response = self.make_request(&check, None).await?;
+        let revised = response.content.unwrap_or_default();
+        eprintln!("[QUEEN] Verified answer: {}", revised);
+        Ok(Some(revised).filter(|r| !r.trim().is_empty()))
+    }
+
+    /// Ask the model to recap the session so far; no tools are offered
+    pub async fn summarize_session(&self, messages: &[Message]) -> Result<String> {
+        let mut history = messages.to_vec();
+        history.push(Message {
+            role: "user".to_string(),
+            content: Some(SUMMARY_PROMPT.to_string()),
+            tool_calls: None,
+            images: None,
+        });
+
+        let response = self.make_request(&history, None).await?;
+        Ok(response.content.unwrap_or_default())
+    }
+
+    /// Have the model draft a numbered plan and loop until the user approves it, amends it or cancels
+    /// On approval the plan and the go-ahead join `messages`; returns the reply to give instead on cancellation
+    async fn approve_plan(&self, messages: &mut Vec<Message>) -> Result<Option<String>> {
+        let mut planning = messages.clone();
+        let mut request = PLAN_PROMPT.to_string();
+        loop {
+            planning.push(Message {
+                role: "user".to_string(),
+                content: Some(request),
+                tool_calls: None,
+                images: None,
+            });
+            let response = self.make_request(&planning, None).await?;
+            let plan = response.content.clone().unwrap_or_default();
+            planning.push(response);
+
+            let answer = ask_approval(&plan).await?;
+            match answer.to_ascii_lowercase().as_str() {
+                "" | "y" | "yes" => {
+                    eprintln!("[QUEEN] Plan approved");
+                    messages.push(Message {
+                        role: "assistant".to_string(),
+                        content: Some(plan),
+                        tool_calls: None,
+                        images: None,
+                    });
+                    messages.push(Message {
+                        role: "user".to_string(),
+                        content: Some(PLAN_APPROVED.to_string()),
+                        tool_calls: None,
+                        images: None,
+                    });
+                    return Ok(None);
+                }
+                "n" | "no" => {
+                    eprintln!("[QUEEN] Plan rejected");
+                    return Ok(Some(PLAN_CANCELLED.to_string()));
+                }
+                _ => {
+                    eprintln!("[QUEEN] Revising plan: {}", answer);
+                    request = format!("Revise the plan with these changes, and reply with only the revised plan: {}", answer);
+                }
+            }
+        }
+    }
+
     /// Run the agentic loop until we get a final response
     pub async fn run_agentic_loop(&self, messages: &mut Vec<Message>) -> Result<String> {
-        let tools = self.get_tools();
+        let user_input = messages.iter().rev().find(|m| m.role == "user").and_then(|m| m.content.clone());
+        let direct = match self.delegation {
+            DelegationMode::On => false,
+            DelegationMode::Off => true,
+            DelegationMode::Auto => user_input.as_deref().is_some_and(is_conversational),
+        };
+        let tools = if direct { Vec::new() } else { self.get_tools() };
         let worker_names: Vec<&str> = self.workers.keys().copied().collect();
+        if direct {
+            eprintln!("[QUEEN] Answering directly: no tools this turn (delegation {})", self.delegation);
+        }
 
         eprintln!("[QUEEN] === Starting Queen's Agentic Loop ===");
         eprintln!("[QUEEN] Available workers: {:?}", worker_names);
 
+        let pruned = context::prune(messages, config::get().max_context_bytes);
+        if pruned > 0 {
+            eprintln!("[QUEEN] Pruned {} old messages to fit the context budget (recent worker results stay recallable)", pruned);
+        }
+
+        idempotency::clear();
+        let budget = Arc::new(LoopBudget::from_config());
+        let turn_start = messages.len();
+        *self.trace.lock().unwrap_or_else(|e| e.into_inner()) = DelegationTrace {
+            user_input: user_input.unwrap_or_default(),
+            ..DelegationTrace::default()
+        };
+        // No tools are offered while planning, so nothing runs until the user signs off
+        if config::get().plan_approve
+            && !direct
+            && let Some(cancelled) = self.approve_plan(messages).await?
+        {
+            self.finish_trace(&cancelled, 0);
+            return Ok(cancelled);
+        }
         let mut iteration = 0;
+        let mut empty_retries = 0;
         loop {
             iteration += 1;
             eprintln!("[QUEEN] --- Iteration {} ---", iteration);
 
+            let reason = budget.exhausted().or_else(|| shutdown::requested().then(|| "hive is shutting down".to_string()));
+            if let Some(reason) = reason {
+                eprintln!("[QUEEN] Stopping: {}", reason);
+                // Only this turn's messages count; earlier answers belong to earlier questions
+                let best = budget::best_so_far(&messages[turn_start..], &reason);
+                self.finish_trace(&best, iteration);
+                return Ok(best);
+            }
+
             // Make request with tools
-            let response = self.make_request(messages, Some(tools.clone())).await?;
+            let response = self.make_request(messages, (!tools.is_empty()).then(|| tools.clone())).await?;
 
             // Add response to message history
             messages.push(response.clone());
 
             // Check if there are tool calls to process
-            if let Some(tool_calls) = &response.tool_calls {
+            if let Some(tool_calls) = response.tool_calls.as_ref().filter(|calls| !calls.is_empty()) {
                 eprintln!("[QUEEN] Received {} tool call(s)", tool_calls.len());
 
-                for tool_call in tool_calls {
+                let limit = config::get().max_tool_calls_per_turn;
+                for (index, tool_call) in tool_calls.iter().enumerate() {
                     let name = &tool_call.function.name;
                     let arguments = &tool_call.function.arguments;
 
+                    if limit > 0 && index >= limit {
+                        eprintln!("[QUEEN] Skipping tool call {} over the limit of {}", name, limit);
+                        messages.push(skipped_tool_call(name, limit));
+                        continue;
+                    }
+
                     eprintln!("[QUEEN] Tool call: {}({})", name, arguments);
 
-                    let result = self.execute_tool_call(name, arguments).await?;
+                    let result = self.execute_tool_call(name, arguments, &budget).await?;
+                    if name == "delegate_to_worker" {
+                        self.remember(arguments, &result);
+                    }
 
                     // Add tool result to messages
                     messages.push(Message {
                         role: "tool".to_string(),
                         content: Some(result),
                         tool_calls: None,
+                        images: None,
                     });
                 }
             } else {
                 // No tool calls - we have the final response
-                let final_response = response.content.unwrap_or_default();
+                let mut final_response = response.content.unwrap_or_default();
+                if final_response.trim().is_empty() {
+                    if empty_retries < MAX_EMPTY_RETRIES {
+                        empty_retries += 1;
+                        eprintln!("[QUEEN] Empty response, nudging model ({}/{})", empty_retries, MAX_EMPTY_RETRIES);
+                        messages.push(Message {
+                            role: "user".to_string(),
+                            content: Some(EMPTY_RESPONSE_NUDGE.to_string()),
+                            tool_calls: None,
+                            images: None,
+                        });
+                        continue;
+                    }
+                    final_response = NO_RESPONSE.to_string();
+                }
+                // Only worth a second look when the answer rests on tool results
+                if config::get().verify_answers
+                    && budget.exhausted().is_none()
+                    && messages[turn_start..].iter().any(|m| m.role == "tool")
+                {
+                    match self.verify_answer(messages).await {
+                        Ok(Some(revised)) => {
+                            if let Some(last) = messages.last_mut() {
+                                last.content = Some(revised.clone());
+                            }
+                            final_response = revised;
+                        }
+                        Ok(None) => {}
+                        Err(e) => eprintln!("[QUEEN] Verification failed, keeping the original answer: {}", e),
+                    }
+                }
                 eprintln!("[QUEEN] === Final Response ===");
                 eprintln!("[QUEEN] {}", final_response);
+                self.finish_trace(&final_response, iteration);
                 return Ok(final_response);
             }
         }
     }
 }
 
+/// First `max_chars` characters of `text`, marking the cut
+fn preview(text: &str, max_chars: usize) -> String {
+    let mut shown: String = text.chars().take(max_chars).collect();
+    if shown.len() < text.len() {
+        shown.push_str("...");
+    }
+    shown
+}
+
+/// Whether a turn reads as conversation the Queen can answer itself: short, with no numbers to compute,
+/// no file names and none of the words that ask for work on the workspace
+fn is_conversational(input: &str) -> bool {
+    let lower = input.to_lowercase();
+    input.chars().count() <= CONVERSATIONAL_CHARS
+        && !input.chars().any(|c| c.is_ascii_digit())
+        && !input.split_whitespace().any(|word| looks_like_path(word.trim_matches(|c: char| !c.is_alphanumeric() && c != '/')))
+        && !lower.split(|c: char| !c.is_alphanumeric()).any(|word| ACTION_WORDS.contains(&word))
+}
+
+/// Evaluate a plain arithmetic expression; anything else (names, functions) is refused rather than guessed at
+fn calculate(expression: &str) -> String {
+    if expression.trim().is_empty() {
+        return "Error: no expression given".to_string();
+    }
+    if let Some(c) = expression.chars().find(|c| !c.is_ascii_digit() && !" .+-*/%^()".contains(*c)) {
+        return format!("Error: '{}' is not allowed; only numbers, + - * / % ^ and parentheses are", c);
+    }
+    match meval::eval_str(expression) {
+        Ok(value) if !value.is_finite() => format!("Error: {} has no finite value (division by zero?)", expression.trim()),
+        // Whole results print without a fractional part while f64 still holds them exactly
+        Ok(value) if value.fract() == 0.0 && value.abs() < MAX_EXACT_INTEGER => format!("{} = {}", expression.trim(), value as i64),
+        Ok(value) => format!("{} = {}", expression.trim(), significant(value)),
+        Err(e) => format!("Error: can't evaluate {}: {}", expression.trim(), e),
+    }
+}
+
+/// `value` to the 15 significant digits an f64 holds reliably, so 0.1 + 0.2 shows as 0.3
+fn significant(value: f64) -> String {
+    let decimals = SIGNIFICANT_DIGITS - (value.abs().log10().floor() as i32 + 1);
+    if decimals <= 0 {
+        return value.to_string();
+    }
+    let rounded = format!("{:.*}", decimals as usize, value);
+    rounded.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Existing files under `base` that `instruction` names, deduplicated and in a stable order
+fn referenced_files(base: &Path, instruction: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = instruction
+        .split(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '`' | ',' | '(' | ')'))
+        .map(|word| word.trim_end_matches(['.', ':', ';', '?', '!']))
+        .filter(|word| !word.is_empty())
+        .map(|word| base.join(word))
+        .filter(|path| path.is_file())
+        .filter_map(|path| path.canonicalize().ok())
+        .filter(|path| path.starts_with(base))
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+/// Paths a worker wrote through its tools or claims in its answer to have written that don't exist under `base`
+fn missing_writes(base: &Path, answer: &str, steps: &[ToolStep]) -> Vec<String> {
+    let mut claimed: Vec<&str> = Vec::new();
+    // A failed write was already reported as one
+    let writes = steps
+        .iter()
+        .filter(|step| WRITE_TOOLS.contains(&step.tool.as_str()) && !step.result.starts_with("Error"));
+    for step in writes {
+        claimed.extend(step.arguments["path"].as_str());
+        claimed.extend(step.arguments["destination"].as_str());
+        if let Some(files) = step.arguments["files"].as_array() {
+            claimed.extend(files.iter().filter_map(|file| file["path"].as_str()));
+        }
+    }
+    for line in answer.lines() {
+        let lower = line.to_lowercase();
+        if !WRITE_CLAIMS.iter().any(|verb| lower.contains(verb)) {
+            continue;
+        }
+        claimed.extend(
+            line.split(|c: char| c.is_whitespace() || matches!(c, '"' | '\'' | '`' | ',' | '(' | ')' | '*'))
+                .map(|word| word.trim_end_matches(['.', ':', ';', '?', '!']))
+                .filter(|word| looks_like_path(word)),
+        );
+    }
+
+    let mut missing: Vec<String> = claimed
+        .into_iter()
+        .filter(|path| base.join(path).symlink_metadata().is_err())
+        .map(str::to_string)
+        .collect();
+    missing.sort();
+    missing.dedup();
+    missing
+}
+
+/// Whether a word from an answer reads as a file path: it has a directory part or an extension, and isn't a URL
+fn looks_like_path(word: &str) -> bool {
+    if word.is_empty() || word.contains("://") {
+        return false;
+    }
+    let extension = word.rsplit_once('.').map(|(stem, ext)| !stem.is_empty() && ext.starts_with(|c: char| c.is_ascii_alphabetic()) && ext.chars().all(|c| c.is_ascii_alphanumeric()));
+    word.contains('/') || extension == Some(true)
+}
+
+/// Append a worker's tool calls to its answer, with long results shortened
+fn with_steps(answer: String, steps: &[ToolStep]) -> String {
+    let steps: Vec<_> = steps
+        .iter()
+        .map(|step| json!({ "tool": step.tool, "arguments": step.arguments, "result": preview(&step.result, TRACE_RESULT_CHARS) }))
+        .collect();
+    format!("{}\n\n[worker trace]\n{}", answer, json!(steps))
+}
+
+/// Ollama treats an untagged model name as `:latest`
+fn with_tag(model: &str) -> Cow<'_, str> {
+    if model.contains(':') {
+        Cow::Borrowed(model)
+    } else {
+        Cow::Owned(format!("{}:latest", model))
+    }
+}
+
+/// Pause the loop to put a question to the user on stdin
+async fn ask_user(question: &str) -> Result<String> {
+    read_answer(format!("\nQueen asks: {}\nYou: ", question)).await
+}
+
+/// Show the Queen's plan and read the user's verdict: empty or "y" approves, "n" cancels, anything else is a change
+async fn ask_approval(plan: &str) -> Result<String> {
+    read_answer(format!("\nQueen's plan:\n{}\n\nRun this plan? [Y/n, or describe changes]: ", plan)).await
+}
+
+/// Show `prompt` and read one line from the user, trimmed
+/// The wait for the user is on a blocking thread, so in-flight work (and a Ctrl-C drain) isn't stuck behind it
+async fn read_answer(prompt: String) -> Result<String> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+    let answer = tokio::task::spawn_blocking(|| {
+        let mut answer = String::new();
+        io::stdin().read_line(&mut answer).map(|_| answer)
+    })
+    .await??;
+    Ok(answer.trim().to_string())
+}
+
+/// Most characters of each worker result echoed by `--show-work`
+const SHOW_WORK_CHARS: usize = 500;
+/// Most characters of each instruction shown when listing recallable results
+const RECALL_LISTING_CHARS: usize = 100;
+/// Most characters of each tool result included when the Queen asks for a worker trace
+const TRACE_RESULT_CHARS: usize = 1000;
+
+/// Worker tools whose `path`, `destination` or `files[].path` arguments should exist once they succeed
+const WRITE_TOOLS: &[&str] = &["write_file", "write_files", "create_directory", "render_template", "insert_at_line", "replace_lines", "reindent"];
+/// Words that mark a line of a worker's answer as claiming a file was written
+const WRITE_CLAIMS: &[&str] = &["wrote", "written", "created", "saved", "updated"];
+
+/// Digits of a fractional `calculate` result worth showing; past this it's floating-point noise
+const SIGNIFICANT_DIGITS: i32 = 15;
+/// Largest magnitude up to which every integer is exactly representable as an f64 (2^53)
+const MAX_EXACT_INTEGER: f64 = 9_007_199_254_740_992.0;
+
+/// Longest message `auto` delegation will treat as conversation
+const CONVERSATIONAL_CHARS: usize = 200;
+/// Words that mark a turn as needing tools under `auto` delegation
+const ACTION_WORDS: &[&str] = &[
+    "read", "write", "open", "create", "delete", "remove", "edit", "change", "fix", "rename", "move", "copy",
+    "run", "execute", "build", "compile", "test", "check", "install", "list", "find", "search", "grep", "show",
+    "file", "files", "folder", "directory", "repo", "project", "code", "git", "commit", "diff", "branch",
+    "calculate", "compute", "download", "fetch", "web", "latest", "today",
+];
+
+/// Similarity above which a misspelled worker name is silently corrected
+const AUTO_CORRECT_SIMILARITY: f64 = 0.85;
+/// Similarity above which two instructions to the same worker count as a repeat
+const SIMILAR_INSTRUCTION: f64 = 0.8;
+/// Similarity above which a misspelled worker name gets a "did you mean" suggestion
+const SUGGEST_SIMILARITY: f64 = 0.5;
+
+const DIRECT_DIRECTIVE: &str = "\n\n# Direct Mode\n\
+Delegation is off: no workers or tools are available. Answer from your own knowledge and the conversation, \
+and say so plainly when a request needs files, commands or the web that you can't reach.";
+
+const CLARIFY_DIRECTIVE: &str = "\n\n# Clarifying Questions\n\
+When a request is ambiguous or missing details you need (which files, what output format, how far to go), \
+call the ask_user tool with a single focused question before delegating. Don't ask about things you can find out yourself.";
+
+const VERIFY_PROMPT: &str = "Before this answer goes to the user, check it against the tool results above. \
+Correct anything they contradict or don't support, and drop claims you can't back up. \
+Reply with only the final answer for the user (unchanged if it was already right), without mentioning this check.";
+
+const PLAN_PROMPT: &str = "Before doing anything, write a numbered plan for this request: which worker handles each step, \
+what it should do, and what you expect back. Don't call any tools yet. Reply with only the plan.";
+
+const PLAN_APPROVED: &str = "The plan is approved. Carry it out now, following the steps in order.";
+
+const PLAN_CANCELLED: &str = "Plan cancelled; nothing was run.";
+
+const SUMMARY_PROMPT: &str = "The session is ending. Summarize it for the user: the files read or changed, \
+commands run, work delegated to each worker, and the conclusions reached. Be concise and use bullet points.";
+
 fn system_prompt() -> &'static str {
 r#"You are the Queen of Hive, a strategic AI orchestrator managing specialized Worker models.
 
@@ -156,13 +1291,13 @@ You receive requests from users and decide how to fulfill them by delegating to
 
 # Available Workers
 {worker_list}
-// Dynamically populated with workers and their capabilities
 
 # Your Capabilities
 - **Delegate to Workers**: Assign tasks to the appropriate Worker based on their capabilities
 - **Execute Code**: Write and run Python or Bash scripts when Workers lack necessary tools
 - **Request Worker Tools**: If a Worker repeatedly fails, you can request their full toolset to attempt the task yourself
 - **Generate Improvements**: When you discover a Worker lacks a capability, log a structured suggestion for a new tool
+- **Calculate**: Use the calculate tool for any arithmetic rather than doing it in your head
 
 # Decision Framework
 1. **Can a Worker handle this?** → Delegate to the most appropriate Worker
@@ -196,4 +1331,24 @@ When you discover a gap in Worker capabilities, generate a JSON suggestion:
 - Prioritize using existing Workers over writing custom code when possible
 
 Your goal is efficient task completion while continuously improving the system's capabilities."#
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculate_evaluates_arithmetic() {
+        assert_eq!(calculate("2 + 3 * 4"), "2 + 3 * 4 = 14");
+        assert_eq!(calculate(" (7 - 1) / 4 "), "(7 - 1) / 4 = 1.5");
+        assert_eq!(calculate("0.1 + 0.2"), "0.1 + 0.2 = 0.3");
+        assert_eq!(calculate("2^10 % 1000"), "2^10 % 1000 = 24");
+    }
+
+    #[test]
+    fn calculate_refuses_what_it_cant_answer() {
+        assert_eq!(calculate("  "), "Error: no expression given");
+        assert_eq!(calculate("sqrt(4)"), "Error: 's' is not allowed; only numbers, + - * / % ^ and parentheses are");
+        assert_eq!(calculate("1/0"), "Error: 1/0 has no finite value (division by zero?)");
+        assert!(calculate("(1 + 2").starts_with("Error: can't evaluate (1 + 2"));
+    }
+}
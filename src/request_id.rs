@@ -0,0 +1,25 @@
+use std::future::Future;
+use uuid::Uuid;
+
+tokio::task_local! {
+    /// ID of the delegation the current task is working on
+    static REQUEST_ID: String;
+}
+
+pub fn new_id() -> String {
+    Uuid::new_v4().to_string()
+}
+
+/// Run `future` with `id` as the current request ID, so every log line inside it can be correlated
+pub async fn scope<F: Future>(id: String, future: F) -> F::Output {
+    REQUEST_ID.scope(id, future).await
+}
+
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Log-prefix fragment (" req=<id>"), empty outside a delegation
+pub fn tag() -> String {
+    current().map(|id| format!(" req={}", id)).unwrap_or_default()
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use crate::{config, request_id};
+
+/// Number of requests currently outstanding against each backend URL
+static IN_FLIGHT: OnceLock<Mutex<HashMap<String, usize>>> = OnceLock::new();
+
+fn in_flight() -> &'static Mutex<HashMap<String, usize>> {
+    IN_FLIGHT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// A reserved slot on a backend; the slot is released when the lease is dropped
+pub struct Lease {
+    url: String,
+}
+
+impl Lease {
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        if let Ok(mut counts) = in_flight().lock()
+            && let Some(count) = counts.get_mut(&self.url)
+        {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+/// Pick the least-loaded URL serving `model`, preferring the agent's own URL on ties
+pub fn acquire(model: &str, default_url: &str) -> Lease {
+    let mut candidates = vec![default_url.to_string()];
+    // A backend override pins every request to one URL
+    if config::get().backend_override.is_none()
+        && let Some(replicas) = config::get().model_replicas.get(model)
+    {
+        candidates.extend(replicas.iter().filter(|url| *url != default_url).cloned());
+    }
+
+    let mut counts = in_flight().lock().unwrap_or_else(|e| e.into_inner());
+    let url = candidates
+        .into_iter()
+        .min_by_key(|url| counts.get(url).copied().unwrap_or(0))
+        .unwrap_or_else(|| default_url.to_string());
+
+    let count = counts.entry(url.clone()).or_insert(0);
+    *count += 1;
+    if *count > 1 || url != default_url {
+        eprintln!("[SCHEDULER{}] Routing {} to {} ({} in flight)", request_id::tag(), model, url, count);
+    }
+
+    Lease { url }
+}
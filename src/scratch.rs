@@ -0,0 +1,48 @@
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use anyhow::{Context, Result};
+use uuid::Uuid;
+
+/// Directory (under a worker's write root) holding throwaway files; removed when the session ends
+pub const SCRATCH_DIR: &str = ".hive-tmp";
+
+/// Create an empty, uniquely named file under `root`'s scratch directory and return its path
+/// `extension` (if any) is appended so tools that go by extension treat the file correctly
+pub fn create(root: &Path, extension: Option<&str>) -> Result<PathBuf> {
+    let dir = root.join(SCRATCH_DIR);
+    fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+    scratch_dirs().lock().unwrap_or_else(|e| e.into_inner()).insert(dir.clone());
+
+    let mut name = format!("scratch-{}", Uuid::new_v4());
+    if let Some(extension) = extension {
+        name.push('.');
+        name.push_str(extension);
+    }
+    let path = dir.join(name);
+    OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .with_context(|| format!("creating {}", path.display()))?;
+    Ok(path)
+}
+
+/// Remove every scratch directory created this session
+pub fn cleanup() {
+    let dirs = std::mem::take(&mut *scratch_dirs().lock().unwrap_or_else(|e| e.into_inner()));
+    for dir in dirs {
+        if let Err(e) = fs::remove_dir_all(&dir)
+            && e.kind() != std::io::ErrorKind::NotFound
+        {
+            eprintln!("Failed to remove scratch directory {}: {}", dir.display(), e);
+        }
+    }
+}
+
+/// Scratch directories handed out so far, so cleanup doesn't depend on which roots were configured
+fn scratch_dirs() -> &'static Mutex<HashSet<PathBuf>> {
+    static DIRS: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+    DIRS.get_or_init(|| Mutex::new(HashSet::new()))
+}
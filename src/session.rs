@@ -0,0 +1,60 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use crate::Message;
+
+/// Bump whenever `Message` (or this envelope) changes shape, and teach `load` to migrate the old one
+pub const SESSION_VERSION: u32 = 1;
+
+/// On-disk envelope for a saved conversation
+#[derive(Serialize, Deserialize)]
+pub struct SessionFile {
+    pub version: u32,
+    /// Unix timestamp (seconds) of when the session was first saved
+    pub created: u64,
+    pub messages: Vec<Message>,
+}
+
+impl SessionFile {
+    pub fn new(messages: Vec<Message>) -> Self {
+        let created = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        SessionFile { version: SESSION_VERSION, created, messages }
+    }
+}
+
+/// Write `messages` as a current-version session first saved at `created`
+pub fn save(path: &Path, created: u64, messages: &[Message]) -> Result<()> {
+    let session = serde_json::json!({
+        "version": SESSION_VERSION,
+        "created": created,
+        "messages": messages,
+    });
+    fs::write(path, serde_json::to_string_pretty(&session)?)?;
+    Ok(())
+}
+
+/// Load a saved session, migrating older formats and refusing ones from a newer Hive
+pub fn load(path: &Path) -> Result<SessionFile> {
+    let text = fs::read_to_string(path).with_context(|| format!("reading session {}", path.display()))?;
+    let value: serde_json::Value =
+        serde_json::from_str(&text).with_context(|| format!("session {} is not valid JSON", path.display()))?;
+
+    // Unversioned sessions were a bare message array (the `--dump-messages` format)
+    if value.is_array() {
+        let messages = serde_json::from_value(value).context("unversioned session has unreadable messages")?;
+        return Ok(SessionFile::new(messages));
+    }
+
+    let version = value["version"].as_u64().unwrap_or(0);
+    if version != SESSION_VERSION as u64 {
+        bail!(
+            "session {} is version {}, but this build reads version {}; start a new session or use a matching Hive",
+            path.display(),
+            version,
+            SESSION_VERSION
+        );
+    }
+    serde_json::from_value(value).with_context(|| format!("session {} has an unreadable body", path.display()))
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Set once the user quits or presses Ctrl-C; from then on no new work starts
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Delegations and tool executions still running, keyed by registration order
+static IN_FLIGHT: OnceLock<Mutex<HashMap<u64, String>>> = OnceLock::new();
+
+fn in_flight() -> &'static Mutex<HashMap<u64, String>> {
+    IN_FLIGHT.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// How often `wait` checks whether the in-flight work has finished
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A piece of work shutdown should wait for; it stops counting when dropped
+pub struct InFlight {
+    id: u64,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Ok(mut map) = in_flight().lock() {
+            map.remove(&self.id);
+        }
+    }
+}
+
+/// Register work (e.g. "file_manager: write_file") so a shutdown waits for it and reports it
+pub fn begin(description: impl Into<String>) -> InFlight {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    in_flight().lock().unwrap_or_else(|e| e.into_inner()).insert(id, description.into());
+    InFlight { id }
+}
+
+/// Stop accepting new work; returns whether this call was the first to ask
+pub fn request() -> bool {
+    !REQUESTED.swap(true, Ordering::SeqCst)
+}
+
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Descriptions of the work running now, oldest first
+pub fn running() -> Vec<String> {
+    let map = in_flight().lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries: Vec<_> = map.iter().collect();
+    entries.sort_by_key(|(id, _)| **id);
+    entries.into_iter().map(|(_, description)| description.clone()).collect()
+}
+
+/// Wait up to `timeout` for in-flight work to finish, returning what was still running when it ran out
+pub async fn wait(timeout: Duration) -> Vec<String> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let running = running();
+        if running.is_empty() || Instant::now() >= deadline {
+            return running;
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// Tool result for a call refused because hive is shutting down
+pub fn refused_tool_call(name: &str) -> String {
+    format!("Error: {} was not run because hive is shutting down. Stop and report what was done so far.", name)
+}
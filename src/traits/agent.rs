@@ -2,7 +2,13 @@ use anyhow::{Result, anyhow};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use crate::Message;
+use tokio::sync::mpsc::UnboundedSender;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::budget::{self, LoopBudget};
+use super::stream;
+use crate::workspace::{STOP_FILE, WorkspaceRoot};
+use crate::{cache, config, idempotency, isolation, json, request_id, scheduler, shutdown, Message};
 
 #[derive(Serialize)]
 pub struct ChatRequest {
@@ -11,11 +17,29 @@ pub struct ChatRequest {
     pub stream: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<Tool>>,
+    /// How long Ollama keeps the model loaded afterwards (e.g. "30m", or -1 for forever)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keep_alive: Option<serde_json::Value>,
+    /// Constrain output: "json", or a JSON schema
+    #[serde(rename = "format", skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<serde_json::Value>,
 }
 
 #[derive(Deserialize)]
 pub struct ChatResponse {
     pub message: Message,
+    #[serde(default)]
+    pub done: bool,
+    /// Why generation stopped: "stop", or "length" when the output hit the context/num_predict limit
+    #[serde(default)]
+    pub done_reason: Option<String>,
+}
+
+impl ChatResponse {
+    /// The model was cut off mid-answer rather than finishing on its own
+    pub fn truncated(&self) -> bool {
+        self.done_reason.as_deref() == Some("length")
+    }
 }
 
 #[derive(Serialize, Clone)]
@@ -32,6 +56,196 @@ pub struct ToolFunction {
     pub parameters: serde_json::Value,
 }
 
+/// Generic tool-use guidance appended to agent system prompts unless an agent opts out
+/// `{max_requests}` is filled in with the configured budget so the model isn't told a limit that doesn't apply
+const REASONING_INSTRUCTIONS: &str = r#"# Reasoning
+- Before each tool call, decide what information you still need and which tool provides it
+- After each tool result, check whether it answers the task before calling another tool
+- If a tool fails, read the error and change your arguments instead of repeating the same call
+- The whole task shares a budget of {max_requests} model requests, so don't spend them on calls you don't need
+- Once you have enough information, stop calling tools and give your final answer"#;
+
+/// The reasoning block with this deployment's limits filled in
+pub fn reasoning_instructions() -> String {
+    let config = config::get();
+    let mut block = REASONING_INSTRUCTIONS.replace("{max_requests}", &config.max_loop_requests.to_string());
+    if config.max_tool_calls_per_turn > 0 {
+        block.push_str(&format!(
+            "\n- Only the first {} tool calls in a response are run; the rest are skipped",
+            config.max_tool_calls_per_turn
+        ));
+    }
+    block
+}
+
+/// Error body returned by Ollama with a non-success status
+#[derive(Debug)]
+pub struct OllamaError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl OllamaError {
+    /// The model isn't pulled or couldn't be loaded (e.g. out of memory), so another model may work
+    pub fn is_model_unavailable(&self) -> bool {
+        let message = self.message.to_ascii_lowercase();
+        self.status == 404
+            || message.contains("not found")
+            || message.contains("failed to load")
+            || message.contains("memory")
+    }
+}
+
+impl std::fmt::Display for OllamaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Ollama returned {}: {}", self.status, self.message)
+    }
+}
+
+impl std::error::Error for OllamaError {}
+
+/// Wait before the first retry of a failed request; later retries wait proportionally longer
+pub const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+
+/// How many times to re-prompt a model that replied with neither text nor tool calls
+pub const MAX_EMPTY_RETRIES: usize = 2;
+pub const EMPTY_RESPONSE_NUDGE: &str = "Your previous response was empty; please answer or call a tool.";
+pub const NO_RESPONSE: &str = "(no response produced)";
+pub const CONTINUE_NUDGE: &str = "Your previous response was cut off by the length limit; continue exactly where it stopped, without repeating anything.";
+/// Appended to a worker's answer that was still cut off after the allowed continuations
+pub const TRUNCATED_NOTE: &str = "\n[response truncated: the model hit its length limit]";
+
+/// Re-prompt sent when JSON mode was requested but the answer didn't parse
+pub fn invalid_json_nudge(error: &serde_json::Error) -> String {
+    format!(
+        "Your previous response was not valid JSON ({}). Reply again with valid JSON only - no prose, comments or code fences.",
+        error
+    )
+}
+
+/// Result for a side-effecting call refused because the operator created the stop file
+pub fn stopped_tool_call(name: &str) -> String {
+    format!(
+        "Error: {} was not run because side effects are paused ({} exists in the workspace). \
+         Reading is still allowed; do not retry changes until the operator removes the file.",
+        name, STOP_FILE
+    )
+}
+
+/// Tool message standing in for a call dropped by the per-response tool call cap
+pub fn skipped_tool_call(name: &str, limit: usize) -> Message {
+    Message {
+        role: "tool".to_string(),
+        content: Some(format!(
+            "Skipped {}: only the first {} tool calls in a response are run; call it again if still needed",
+            name, limit
+        )),
+        tool_calls: None,
+        images: None,
+    }
+}
+
+/// One tool call a worker made while working on an instruction
+#[derive(Clone, Debug, Serialize)]
+pub struct ToolStep {
+    pub tool: String,
+    pub arguments: serde_json::Value,
+    pub result: String,
+    /// Whether the tool can change state (see `Agent::has_side_effects`)
+    pub side_effects: bool,
+}
+
+/// A worker's request to hand its task to a better-suited worker instead of answering
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Escalation {
+    pub escalate: bool,
+    pub suggested_worker: Option<String>,
+    pub reason: String,
+}
+
+impl Escalation {
+    /// The escalation a worker's result carries, if it is one
+    pub fn parse(result: &str) -> Option<Escalation> {
+        serde_json::from_str::<Escalation>(result.trim()).ok().filter(|e| e.escalate)
+    }
+
+    /// Tool offered to agents that may escalate
+    pub fn tool() -> Tool {
+        Tool {
+            tool_type: "function".to_string(),
+            function: ToolFunction {
+                name: ESCALATE_TOOL.to_string(),
+                description: "Hand the task back when it is beyond your tools or role (for example interpreting code when you only manage files), instead of guessing".to_string(),
+                parameters: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "suggested_worker": {
+                            "type": "string",
+                            "description": "The kind of worker that could do this, if you know (e.g. shell, web_search)"
+                        },
+                        "reason": {
+                            "type": "string",
+                            "description": "What you couldn't do and why"
+                        }
+                    },
+                    "required": ["reason"]
+                }),
+            },
+        }
+    }
+}
+
+/// Name of the tool a worker calls to escalate
+pub const ESCALATE_TOOL: &str = "escalate";
+
+/// Per-delegation settings the Queen passes down to a worker's run
+#[derive(Clone, Debug, Default)]
+pub struct RunOptions {
+    /// Soft cap on the length of the final answer; longer answers are truncated
+    pub max_chars: Option<usize>,
+    /// Receives a short partial result after each tool call, for live progress
+    pub progress: Option<UnboundedSender<String>>,
+    /// Loop-wide limits inherited from the Queen; the worker stops early once they're spent
+    pub budget: Option<Arc<LoopBudget>>,
+    /// Ask Ollama for JSON output and normalize the final answer as JSON
+    pub json_mode: bool,
+    /// Identifies the delegation across retries, so side-effecting tool calls aren't applied twice
+    pub idempotency_key: Option<String>,
+    /// Collects every tool call and its result, for callers that want to see how the answer was reached
+    pub trace: Option<Arc<Mutex<Vec<ToolStep>>>>,
+}
+
+impl ToolFunction {
+    /// Check that `args` supplies every required parameter with the declared JSON type
+    pub fn validate(&self, args: &serde_json::Value) -> std::result::Result<(), String> {
+        if !args.is_object() {
+            return Err("arguments must be an object".to_string());
+        }
+
+        let required = self.parameters["required"].as_array().cloned().unwrap_or_default();
+        for field in required.iter().filter_map(|f| f.as_str()) {
+            let value = &args[field];
+            if value.is_null() {
+                return Err(format!("missing required argument '{}'", field));
+            }
+            let matches = match self.parameters["properties"][field]["type"].as_str() {
+                Some("string") => value.is_string(),
+                Some("integer") => value.is_i64() || value.is_u64(),
+                Some("number") => value.is_number(),
+                Some("boolean") => value.is_boolean(),
+                Some("array") => value.is_array(),
+                Some("object") => value.is_object(),
+                _ => true,
+            };
+            if !matches {
+                return Err(format!("argument '{}' has the wrong type", field));
+            }
+        }
+
+        Ok(())
+    }
+}
+
 #[async_trait]
 pub trait Agent: Send + Sync {
     // Required: Basic configuration
@@ -40,6 +254,72 @@ pub trait Agent: Send + Sync {
     fn system_prompt(&self) -> &'static str;
     fn client(&self) -> Client;
 
+    // Optional: Return false to send the system prompt without the reasoning block
+    // By default it's included unless the agent is listed in `skip_reasoning`
+    fn append_reasoning_instructions(&self) -> bool {
+        !config::get().skip_reasoning.iter().any(|name| name == self.config_name())
+    }
+
+    // Full system prompt sent to the model; empty means no system message at all
+    fn build_system_prompt(&self) -> String {
+        let base = self.system_prompt().trim();
+        match (base.is_empty(), self.append_reasoning_instructions()) {
+            (_, false) => base.to_string(),
+            (true, true) => reasoning_instructions(),
+            (false, true) => format!("{}\n\n{}", base, reasoning_instructions()),
+        }
+    }
+
+    // Optional: Name of a configured backend to prefer over `ollama_url`
+    fn backend(&self) -> Option<&'static str> {
+        None
+    }
+
+    // Resolve the URL to send requests to, honoring a backend override, then the preferred backend
+    fn resolve_url(&self) -> String {
+        if let Some(url) = &config::get().backend_override {
+            return url.clone();
+        }
+        self.backend()
+            .and_then(|name| config::get().backend(name))
+            .map(|b| b.url.clone())
+            .unwrap_or_else(|| self.ollama_url().to_string())
+    }
+
+    // Model requests actually go to: the configured override if there is one, else `model()`
+    fn effective_model(&self) -> &str {
+        config::get().model_override.as_deref().unwrap_or(self.model())
+    }
+
+    // Optional: Models to try in order; defaults to `effective_model()` followed by its configured fallbacks
+    fn models(&self) -> Vec<&str> {
+        let mut models = vec![self.effective_model()];
+        if let Some(fallbacks) = config::get().fallback_models.get(self.effective_model()) {
+            models.extend(fallbacks.iter().map(String::as_str));
+        }
+        models
+    }
+
+    // Optional: Name per-agent request settings (timeouts, retries) are looked up under
+    fn config_name(&self) -> &'static str {
+        "*"
+    }
+
+    // Optional: Extra HTTP headers (e.g. auth for a proxied backend); defaults to the config entries for this agent
+    fn headers(&self) -> Vec<(String, String)> {
+        config::get().headers_for(self.config_name())
+    }
+
+    // Optional: Keep-alive sent with each request; defaults to the config entry for this model
+    fn keep_alive(&self) -> Option<String> {
+        config::get().keep_alive_for(self.effective_model()).map(str::to_string)
+    }
+
+    // Optional: Return true if this agent should always answer in JSON
+    fn json_mode(&self) -> bool {
+        false
+    }
+
     // Optional: Override to provide tools this agent can use
     fn get_tools(&self) -> Vec<Tool> {
         vec![]
@@ -50,79 +330,354 @@ pub trait Agent: Send + Sync {
         Err(anyhow!("Unknown tool: {}", name))
     }
 
+    // Run a tool here, or in this agent's helper process when its role is listed in `isolated_workers`
+    fn dispatch_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
+        if isolation::isolated(self.config_name()) {
+            isolation::execute(self.config_name(), name, args)
+        } else {
+            self.execute_tool(name, args)
+        }
+    }
+
+    // Optional: Return false to withhold the escalate tool, so the agent always answers itself
+    fn can_escalate(&self) -> bool {
+        true
+    }
+
+    // Optional: Override to mark tools that change state, so retries don't repeat them
+    fn has_side_effects(&self, _name: &str) -> bool {
+        false
+    }
+
     // Core: Make a single LLM request
-    async fn make_request(&self, messages: &Vec<Message>, tools: Option<Vec<Tool>>) -> Result<Message> {
-        let request = ChatRequest {
-            model: self.model().to_string(),
-            messages: messages.clone(),
+    async fn make_request(&self, messages: &[Message], tools: Option<Vec<Tool>>) -> Result<Message> {
+        self.make_request_with_format(messages, tools, None)
+            .await
+            .map(|response| response.message)
+    }
+
+    // Core: Make a single LLM request with an optional output format constraint
+    async fn make_request_with_format(
+        &self,
+        messages: &[Message],
+        tools: Option<Vec<Tool>>,
+        response_format: Option<serde_json::Value>,
+    ) -> Result<ChatResponse> {
+        let mut request = ChatRequest {
+            model: self.effective_model().to_string(),
+            messages: messages.to_vec(),
             stream: false,
             tools,
+            // Ollama takes bare numbers as seconds and anything else as a duration string
+            keep_alive: self.keep_alive().map(|k| match k.parse::<i64>() {
+                Ok(seconds) => serde_json::json!(seconds),
+                Err(_) => serde_json::json!(k),
+            }),
+            response_format,
         };
 
-        let response = self
-            .client()
-            .post(self.ollama_url())
-            .json(&request)
-            .send()
-            .await?
-            .json::<ChatResponse>()
-            .await?;
+        let cache_key = if config::get().cache_requests {
+            cache::request_key(&request)
+        } else {
+            None
+        };
+        if let Some(cached) = cache_key.and_then(cache::get) {
+            eprintln!("[DEBUG{}] Cache hit for {} request", request_id::tag(), self.effective_model());
+            return Ok(ChatResponse { message: cached, done: true, done_reason: None });
+        }
 
-        Ok(response.message)
+        let models = self.models();
+        let mut last_error = None;
+        for (index, model) in models.iter().enumerate() {
+            request.model = model.to_string();
+            match self.send_request(&request).await {
+                Ok(response) => {
+                    if index > 0 {
+                        eprintln!("[DEBUG{}] Used fallback model {} instead of {}", request_id::tag(), model, self.effective_model());
+                    }
+                    if response.truncated() {
+                        eprintln!("[DEBUG{}] Response from {} was cut off at the length limit", request_id::tag(), model);
+                    } else if let Some(key) = cache_key {
+                        cache::insert(key, response.message.clone());
+                    }
+                    return Ok(response);
+                }
+                Err(e) => match e.downcast_ref::<OllamaError>() {
+                    Some(ollama) if ollama.is_model_unavailable() && index + 1 < models.len() => {
+                        eprintln!("[DEBUG{}] Model {} unavailable ({}), trying {}", request_id::tag(), model, ollama.message, models[index + 1]);
+                        last_error = Some(e);
+                    }
+                    _ => return Err(e),
+                },
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| anyhow!("No models configured for this agent")))
+    }
+
+    // Send one request, falling back to other backends hosting the model if the first is unreachable
+    async fn send_request(&self, request: &ChatRequest) -> Result<ChatResponse> {
+        let primary = self.resolve_url();
+        let mut urls = vec![primary.clone()];
+        urls.extend(config::get().fallback_urls(&request.model, &primary));
+
+        let config = config::get();
+        let timeout = config.request_timeout(self.config_name());
+        let max_retries = config.request_retries_for(self.config_name());
+        let mut attempt = 0;
+        let mut retries = 0;
+        loop {
+            let lease = scheduler::acquire(&request.model, &urls[attempt]);
+            budget::count_request();
+            let mut builder = self.client().post(lease.url()).json(request);
+            // Never logged, since they usually carry credentials
+            for (name, value) in self.headers() {
+                builder = builder.header(name, value);
+            }
+            if let Some(timeout) = timeout {
+                builder = builder.timeout(timeout);
+            }
+            let failure = match builder.send().await {
+                // Read as text and reassembled, so a streamed body works as well as a single object
+                Ok(response) if response.status().is_success() => match response.text().await {
+                    Ok(body) => return stream::collect(&body),
+                    Err(e) if e.is_timeout() => anyhow::Error::from(e),
+                    Err(e) => return Err(e.into()),
+                },
+                Ok(response) => {
+                    let status = response.status().as_u16();
+                    let body = response.text().await.unwrap_or_default();
+                    // Ollama reports errors as {"error": "..."}
+                    let message = serde_json::from_str::<serde_json::Value>(&body)
+                        .ok()
+                        .and_then(|v| v["error"].as_str().map(str::to_string))
+                        .unwrap_or(body);
+                    let error = OllamaError { status, message };
+                    // A missing model won't appear by retrying; leave that to the model fallbacks
+                    if status < 500 || error.is_model_unavailable() {
+                        return Err(error.into());
+                    }
+                    error.into()
+                }
+                // Only an unreachable backend is worth retrying elsewhere
+                Err(e) if e.is_connect() && attempt + 1 < urls.len() => {
+                    eprintln!("[DEBUG{}] Backend {} unreachable, falling back to {}", request_id::tag(), lease.url(), urls[attempt + 1]);
+                    attempt += 1;
+                    continue;
+                }
+                Err(e) if e.is_timeout() => e.into(),
+                Err(e) => return Err(e.into()),
+            };
+
+            if retries >= max_retries {
+                return Err(failure);
+            }
+            retries += 1;
+            eprintln!("[DEBUG{}] Request to {} failed ({}), retrying ({}/{})", request_id::tag(), request.model, failure, retries, max_retries);
+            // Free the backend slot while backing off
+            drop(lease);
+            tokio::time::sleep(RETRY_BACKOFF * retries).await;
+        }
     }
 
     // Agentic loop: Process an instruction using this agent's tools
-    async fn run(&self, instruction: &str) -> Result<String> {
-        let tools = self.get_tools();
+    async fn run(&self, instruction: &str, options: &RunOptions) -> Result<String> {
+        let mut tools = self.get_tools();
+        if self.can_escalate() {
+            tools.push(Escalation::tool());
+        }
         let tools_option = if tools.is_empty() { None } else { Some(tools.clone()) };
 
-        eprintln!("[DEBUG] Agent starting with instruction: {}", instruction);
-        eprintln!("[DEBUG] Available tools: {:?}", tools.iter().map(|t| &t.function.name).collect::<Vec<_>>());
+        eprintln!("[DEBUG{}] Agent starting with instruction: {}", request_id::tag(), instruction);
+        eprintln!("[DEBUG{}] Available tools: {:?}", request_id::tag(), tools.iter().map(|t| &t.function.name).collect::<Vec<_>>());
 
-        let mut messages = vec![
-            Message {
+        let mut system_prompt = self.build_system_prompt();
+        if let Some(max_chars) = options.max_chars {
+            system_prompt.push_str(&format!("\n\nKeep your final answer under {} characters.", max_chars));
+        }
+
+        let mut messages = Vec::new();
+        if !system_prompt.trim().is_empty() {
+            messages.push(Message {
                 role: "system".to_string(),
-                content: Some(self.system_prompt().to_string()),
-                tool_calls: None,
-            },
-            Message {
-                role: "user".to_string(),
-                content: Some(instruction.to_string()),
+                content: Some(system_prompt.trim().to_string()),
                 tool_calls: None,
-            },
-        ];
+                images: None,
+            });
+        }
+        messages.push(Message {
+            role: "user".to_string(),
+            content: Some(instruction.to_string()),
+            tool_calls: None,
+            images: None,
+        });
+
+        let json_mode = options.json_mode || self.json_mode();
+        let response_format = json_mode.then(|| serde_json::json!("json"));
 
         let mut iteration = 0;
+        let mut empty_retries = 0;
+        let mut side_effect_calls = 0;
+        let mut continuations = 0;
+        let mut json_retries = 0;
+        // Text of earlier responses that were cut off, to be joined with the one that finishes
+        let mut partial = String::new();
         loop {
             iteration += 1;
-            eprintln!("[DEBUG] === Iteration {} ===", iteration);
+            eprintln!("[DEBUG{}] === Iteration {} ===", request_id::tag(), iteration);
+
+            if let Some(reason) = options.budget.as_ref().and_then(|b| b.exhausted()) {
+                eprintln!("[DEBUG{}] Stopping: {}", request_id::tag(), reason);
+                return Ok(budget::best_so_far(&messages, &reason));
+            }
+            if shutdown::requested() {
+                eprintln!("[DEBUG{}] Stopping: hive is shutting down", request_id::tag());
+                return Ok(budget::best_so_far(&messages, "hive is shutting down"));
+            }
 
-            let response = self.make_request(&messages, tools_option.clone()).await?;
+            let chat = self
+                .make_request_with_format(&messages, tools_option.clone(), response_format.clone())
+                .await?;
+            let truncated = chat.truncated();
+            let response = chat.message;
             messages.push(response.clone());
 
-            if let Some(tool_calls) = &response.tool_calls {
-                eprintln!("[DEBUG] Received {} tool call(s)", tool_calls.len());
+            if let Some(tool_calls) = response.tool_calls.as_ref().filter(|calls| !calls.is_empty()) {
+                eprintln!("[DEBUG{}] Received {} tool call(s)", request_id::tag(), tool_calls.len());
 
-                for tool_call in tool_calls {
+                let limit = config::get().max_tool_calls_per_turn;
+                for (index, tool_call) in tool_calls.iter().enumerate() {
                     let name = &tool_call.function.name;
                     let arguments = &tool_call.function.arguments;
 
-                    eprintln!("[DEBUG] Tool call: {}({})", name, arguments);
+                    if limit > 0 && index >= limit {
+                        eprintln!("[DEBUG{}] Skipping tool call {} over the limit of {}", request_id::tag(), name, limit);
+                        messages.push(skipped_tool_call(name, limit));
+                        continue;
+                    }
+
+                    eprintln!("[DEBUG{}] Tool call: {}({})", request_id::tag(), name, arguments);
+
+                    if name == ESCALATE_TOOL && self.can_escalate() {
+                        let escalation = Escalation {
+                            escalate: true,
+                            suggested_worker: arguments["suggested_worker"].as_str().map(str::to_string),
+                            reason: arguments["reason"].as_str().unwrap_or("").to_string(),
+                        };
+                        eprintln!("[DEBUG{}] Escalating: {}", request_id::tag(), escalation.reason);
+                        return Ok(serde_json::to_string(&escalation)?);
+                    }
 
-                    let result = self.execute_tool(name, arguments)?;
+                    let _running = shutdown::begin(format!("{}: {}", self.config_name(), name));
+                    let result = if self.has_side_effects(name) && WorkspaceRoot::shared().stop_requested() {
+                        eprintln!("[DEBUG{}] Refusing {}: {} is present", request_id::tag(), name, STOP_FILE);
+                        stopped_tool_call(name)
+                    } else if self.has_side_effects(name) && shutdown::requested() {
+                        eprintln!("[DEBUG{}] Refusing {}: hive is shutting down", request_id::tag(), name);
+                        shutdown::refused_tool_call(name)
+                    } else if let Some(delegation) = options.idempotency_key.as_deref().filter(|_| self.has_side_effects(name)) {
+                        side_effect_calls += 1;
+                        let key = idempotency::operation_key(side_effect_calls, name, arguments);
+                        match idempotency::completed(delegation, key) {
+                            Some(prior) => {
+                                eprintln!("[DEBUG{}] {} already applied by an earlier attempt, skipping", request_id::tag(), name);
+                                prior
+                            }
+                            None => {
+                                let result = self.dispatch_tool(name, arguments)?;
+                                idempotency::record(delegation, key, &result);
+                                result
+                            }
+                        }
+                    } else {
+                        self.dispatch_tool(name, arguments)?
+                    };
 
-                    eprintln!("[DEBUG] Tool result: {}", result);
+                    eprintln!("[DEBUG{}] Tool result: {}", request_id::tag(), result);
+
+                    if let Some(trace) = &options.trace {
+                        trace.lock().unwrap_or_else(|e| e.into_inner()).push(ToolStep {
+                            tool: name.clone(),
+                            arguments: arguments.clone(),
+                            result: result.clone(),
+                            side_effects: self.has_side_effects(name),
+                        });
+                    }
+
+                    if let Some(progress) = &options.progress {
+                        let preview: String = result.chars().take(200).collect();
+                        let _ = progress.send(format!("{}: {}", name, preview));
+                    }
 
                     messages.push(Message {
                         role: "tool".to_string(),
                         content: Some(result),
                         tool_calls: None,
+                        images: None,
                     });
                 }
             } else {
+                // Ask a cut-off model to pick up where it stopped, up to the configured bound
+                let max_continuations = config::get().max_continuations;
+                if truncated && continuations < max_continuations {
+                    continuations += 1;
+                    eprintln!("[DEBUG{}] Response truncated, requesting continuation ({}/{})", request_id::tag(), continuations, max_continuations);
+                    partial.push_str(response.content.as_deref().unwrap_or_default());
+                    messages.push(Message {
+                        role: "user".to_string(),
+                        content: Some(CONTINUE_NUDGE.to_string()),
+                        tool_calls: None,
+                        images: None,
+                    });
+                    continue;
+                }
+
                 // No tool calls - return final response
-                let final_response = response.content.unwrap_or_default();
-                eprintln!("[DEBUG] Final response: {}", final_response);
+                let mut final_response = std::mem::take(&mut partial) + response.content.as_deref().unwrap_or_default();
+                if final_response.trim().is_empty() {
+                    if empty_retries < MAX_EMPTY_RETRIES {
+                        empty_retries += 1;
+                        eprintln!("[DEBUG{}] Empty response, nudging model ({}/{})", request_id::tag(), empty_retries, MAX_EMPTY_RETRIES);
+                        messages.push(Message {
+                            role: "user".to_string(),
+                            content: Some(EMPTY_RESPONSE_NUDGE.to_string()),
+                            tool_calls: None,
+                            images: None,
+                        });
+                        continue;
+                    }
+                    final_response = NO_RESPONSE.to_string();
+                }
+                if json_mode {
+                    let max_json_retries = config::get().max_json_retries;
+                    match json::parse_lenient(&final_response) {
+                        Ok(value) => final_response = value.to_string(),
+                        Err(e) if json_retries < max_json_retries => {
+                            json_retries += 1;
+                            eprintln!("[DEBUG{}] Invalid JSON ({}), re-asking ({}/{})", request_id::tag(), e, json_retries, max_json_retries);
+                            messages.push(Message {
+                                role: "user".to_string(),
+                                content: Some(invalid_json_nudge(&e)),
+                                tool_calls: None,
+                                images: None,
+                            });
+                            continue;
+                        }
+                        Err(e) => eprintln!("[DEBUG{}] Giving up on JSON after {} retries: {}", request_id::tag(), json_retries, e),
+                    }
+                }
+                if let Some(max_chars) = options.max_chars
+                    && final_response.len() > max_chars
+                {
+                    let cut = final_response.floor_char_boundary(max_chars);
+                    final_response.truncate(cut);
+                    final_response.push_str(&format!("... [truncated to {} chars]", max_chars));
+                }
+                if truncated {
+                    eprintln!("[DEBUG{}] Still truncated after {} continuation(s)", request_id::tag(), continuations);
+                    final_response.push_str(TRUNCATED_NOTE);
+                }
+                eprintln!("[DEBUG{}] Final response: {}", request_id::tag(), final_response);
                 return Ok(final_response);
             }
         }
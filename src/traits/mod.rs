@@ -1,5 +1,7 @@
 mod worker;
 mod agent;
+mod stream;
 
-pub use agent::{Agent, Tool, ToolFunction};
-pub use worker::{Worker, WorkerFactory};
\ No newline at end of file
+pub use agent::{Agent, Escalation, OllamaError, RunOptions, Tool, ToolFunction, ToolStep, EMPTY_RESPONSE_NUDGE, MAX_EMPTY_RETRIES, NO_RESPONSE, skipped_tool_call};
+pub use stream::StreamAccumulator;
+pub use worker::{Capabilities, ToolRunner, Worker, WorkerFactory};
\ No newline at end of file
@@ -0,0 +1,158 @@
+use anyhow::{Result, anyhow};
+use serde_json::Value;
+use crate::{FunctionCall, Message, ToolCall};
+use super::agent::ChatResponse;
+
+/// Builds one response out of streamed chunks: content fragments are joined, and tool call deltas are
+/// merged by `index` (their `function.name` and `function.arguments` fragments concatenated) so no call
+/// is parsed or dispatched until all of it has arrived
+#[derive(Default)]
+pub struct StreamAccumulator {
+    role: Option<String>,
+    content: String,
+    calls: Vec<PartialCall>,
+    done: bool,
+    done_reason: Option<String>,
+}
+
+/// A tool call still being streamed
+#[derive(Default)]
+struct PartialCall {
+    name: String,
+    /// Argument text received so far, when it arrives as string fragments
+    arguments: String,
+    /// Arguments sent whole as a JSON object, as Ollama does
+    value: Option<Value>,
+}
+
+impl StreamAccumulator {
+    /// Fold in one chunk (`{"message": {...}, "done": ...}`)
+    pub fn push(&mut self, chunk: &Value) {
+        let message = &chunk["message"];
+        if let Some(role) = message["role"].as_str().filter(|role| !role.is_empty()) {
+            self.role.get_or_insert_with(|| role.to_string());
+        }
+        if let Some(text) = message["content"].as_str() {
+            self.content.push_str(text);
+        }
+        for call in message["tool_calls"].as_array().into_iter().flatten() {
+            // Deltas name the call they continue; an entry without an index is a call of its own
+            let index = call["index"].as_u64().map_or(self.calls.len(), |index| index as usize);
+            if index >= self.calls.len() {
+                self.calls.resize_with(index + 1, PartialCall::default);
+            }
+            let partial = &mut self.calls[index];
+            if let Some(name) = call["function"]["name"].as_str() {
+                partial.name.push_str(name);
+            }
+            match &call["function"]["arguments"] {
+                Value::String(fragment) => partial.arguments.push_str(fragment),
+                Value::Null => {}
+                whole => partial.value = Some(whole.clone()),
+            }
+        }
+        if chunk["done"].as_bool() == Some(true) {
+            self.done = true;
+            self.done_reason = chunk["done_reason"].as_str().map(str::to_string);
+        }
+    }
+
+    /// The complete response; fails if a tool call has no name or its arguments aren't valid JSON once joined
+    pub fn finish(self) -> Result<ChatResponse> {
+        let mut tool_calls = Vec::new();
+        for (index, call) in self.calls.into_iter().enumerate() {
+            if call.name.is_empty() {
+                return Err(anyhow!("streamed tool call {} never received a name", index));
+            }
+            let arguments = match call.value {
+                Some(value) => value,
+                None if call.arguments.trim().is_empty() => Value::Object(Default::default()),
+                None => serde_json::from_str(&call.arguments)
+                    .map_err(|e| anyhow!("arguments of streamed tool call '{}' are not valid JSON ({}): {}", call.name, e, call.arguments))?,
+            };
+            tool_calls.push(ToolCall { function: FunctionCall { name: call.name, arguments } });
+        }
+
+        Ok(ChatResponse {
+            message: Message {
+                role: self.role.unwrap_or_else(|| "assistant".to_string()),
+                content: Some(self.content),
+                tool_calls: (!tool_calls.is_empty()).then_some(tool_calls),
+                images: None,
+            },
+            done: self.done,
+            done_reason: self.done_reason,
+        })
+    }
+}
+
+/// Read a response body: one JSON object when not streamed, or one chunk per line when streamed
+pub fn collect(body: &str) -> Result<ChatResponse> {
+    let mut accumulator = StreamAccumulator::default();
+    match serde_json::from_str::<Value>(body) {
+        Ok(whole) => accumulator.push(&whole),
+        Err(_) => {
+            for line in body.lines().filter(|line| !line.trim().is_empty()) {
+                let chunk = serde_json::from_str::<Value>(line).map_err(|e| anyhow!("invalid chunk in streamed response ({}): {}", e, line))?;
+                accumulator.push(&chunk);
+            }
+        }
+    }
+    accumulator.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn merges_a_tool_call_split_across_chunks() {
+        let chunks = [
+            json!({ "message": { "role": "assistant", "content": "", "tool_calls": [
+                { "index": 0, "function": { "name": "read_", "arguments": "{\"pa" } }
+            ] }, "done": false }),
+            json!({ "message": { "content": "", "tool_calls": [
+                { "index": 0, "function": { "name": "file", "arguments": "th\": \"src/" } },
+                { "index": 1, "function": { "name": "list_files", "arguments": "" } }
+            ] }, "done": false }),
+            json!({ "message": { "content": "", "tool_calls": [
+                { "index": 0, "function": { "arguments": "main.rs\"}" } }
+            ] }, "done": true, "done_reason": "stop" }),
+        ];
+        let body: Vec<String> = chunks.iter().map(Value::to_string).collect();
+
+        let response = collect(&body.join("\n")).unwrap();
+        let calls = response.message.tool_calls.unwrap();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].function.name, "read_file");
+        assert_eq!(calls[0].function.arguments, json!({ "path": "src/main.rs" }));
+        assert_eq!(calls[1].function.name, "list_files");
+        assert_eq!(calls[1].function.arguments, json!({}));
+        assert!(response.done);
+        assert_eq!(response.done_reason.as_deref(), Some("stop"));
+    }
+
+    #[test]
+    fn takes_whole_object_arguments_as_sent() {
+        let body = json!({ "message": { "role": "assistant", "content": "", "tool_calls": [
+            { "function": { "name": "read_file", "arguments": { "path": "Cargo.toml" } } }
+        ] }, "done": true });
+
+        let response = collect(&body.to_string()).unwrap();
+        let calls = response.message.tool_calls.unwrap();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].function.name, "read_file");
+        assert_eq!(calls[0].function.arguments, json!({ "path": "Cargo.toml" }));
+    }
+
+    #[test]
+    fn invalid_joined_arguments_fail_in_finish() {
+        let mut accumulator = StreamAccumulator::default();
+        accumulator.push(&json!({ "message": { "tool_calls": [{ "index": 0, "function": { "name": "read_file", "arguments": "{\"path\": " } }] } }));
+        accumulator.push(&json!({ "message": { "tool_calls": [{ "index": 0, "function": { "arguments": "\"a.txt\"" } }] }, "done": true }));
+
+        let error = accumulator.finish().err().unwrap().to_string();
+        assert!(error.contains("read_file"), "{}", error);
+    }
+}
@@ -1,8 +1,42 @@
 use anyhow::Result;
 use async_trait::async_trait;
+use tokio::sync::mpsc::UnboundedSender;
+use crate::traits::{Agent, RunOptions, Tool};
+use crate::{config, output};
+
+/// What a worker can touch, shown to the operator at startup
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Capabilities {
+    pub reads: bool,
+    pub writes: bool,
+    pub network: bool,
+    pub shell: bool,
+}
+
+impl Capabilities {
+    /// Names of the granted capabilities, e.g. `["reads", "writes"]`
+    pub fn names(&self) -> Vec<&'static str> {
+        [("reads", self.reads), ("writes", self.writes), ("network", self.network), ("shell", self.shell)]
+            .into_iter()
+            .filter_map(|(name, granted)| granted.then_some(name))
+            .collect()
+    }
+}
+
+/// Runs a worker's tools by name outside its own loop, as an isolated worker's helper process does
+/// Every `Agent` gets this from `execute_tool`; a worker that isn't one has to say how its tools run
+pub trait ToolRunner {
+    fn run_tool(&self, name: &str, args: &serde_json::Value) -> Result<String>;
+}
+
+impl<T: Agent> ToolRunner for T {
+    fn run_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
+        self.execute_tool(name, args)
+    }
+}
 
 #[async_trait]
-pub trait Worker: Send + Sync {
+pub trait Worker: Send + Sync + ToolRunner {
     /// Unique identifier for this worker
     fn role(&self) -> &'static str;
 
@@ -11,7 +45,53 @@ pub trait Worker: Send + Sync {
 
     /// Process an instruction and return the result
     /// Workers implement this using their own Agent capabilities
-    async fn process(&self, instruction: &str) -> Result<String>;
+    async fn process(&self, instruction: &str, options: &RunOptions) -> Result<String>;
+
+    /// Access this worker has beyond talking to its model; none by default
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Model the worker runs on, if it's model-backed
+    fn model_name(&self) -> Option<&'static str> {
+        None
+    }
+
+    /// Rewrite an instruction before `process` runs it (add house rules, strip markdown, ...)
+    fn preprocess_instruction(&self, instruction: &str) -> String {
+        instruction.to_string()
+    }
+
+    /// Tidy a result before it goes back to the Queen; by default strips reasoning
+    /// tags and lone code fences for roles listed in `clean_output`
+    fn postprocess_output(&self, result: String) -> String {
+        if config::get().clean_output.iter().any(|role| role == self.role()) {
+            output::clean(&result)
+        } else {
+            result
+        }
+    }
+
+    /// Tools this worker can use, shown to the Queen so it knows what to ask for
+    /// Agent-backed workers should return `Agent::get_tools(self)`
+    fn get_tools(&self) -> Vec<Tool> {
+        Vec::new()
+    }
+
+    /// Like `process`, but sends partial results over `progress` as they become available
+    /// Agent-backed workers get this for free; others just send nothing before returning
+    async fn process_streaming(
+        &self,
+        instruction: &str,
+        options: &RunOptions,
+        progress: UnboundedSender<String>,
+    ) -> Result<String> {
+        let options = RunOptions {
+            progress: Some(progress),
+            ..options.clone()
+        };
+        self.process(instruction, &options).await
+    }
 }
 
 /// Factory function type for creating workers
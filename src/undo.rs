@@ -0,0 +1,87 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use crate::config;
+
+/// Directory (under the workspace root) holding snapshots and the undo log
+pub const UNDO_DIR: &str = ".hive-undo";
+const LOG_FILE: &str = "log.json";
+
+/// One recorded mutation: the path touched and whether it existed beforehand
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    id: u64,
+    path: PathBuf,
+    existed: bool,
+}
+
+fn load_log(dir: &Path) -> Vec<Entry> {
+    fs::read_to_string(dir.join(LOG_FILE))
+        .ok()
+        .and_then(|text| serde_json::from_str(&text).ok())
+        .unwrap_or_default()
+}
+
+fn save_log(dir: &Path, log: &[Entry]) -> Result<()> {
+    fs::write(dir.join(LOG_FILE), serde_json::to_string_pretty(log)?)?;
+    Ok(())
+}
+
+fn snapshot_path(dir: &Path, id: u64) -> PathBuf {
+    dir.join(format!("{}.bak", id))
+}
+
+/// Snapshot `path` before it is modified so `undo_last` can restore it
+pub fn record(root: &Path, path: &Path) -> Result<()> {
+    let depth = config::get().undo_history;
+    if depth == 0 {
+        return Ok(());
+    }
+
+    let dir = root.join(UNDO_DIR);
+    fs::create_dir_all(&dir)?;
+    let mut log = load_log(&dir);
+
+    let id = log.last().map(|e| e.id + 1).unwrap_or(0);
+    let existed = path.is_file();
+    if existed {
+        fs::copy(path, snapshot_path(&dir, id))
+            .with_context(|| format!("snapshotting {}", path.display()))?;
+    }
+    log.push(Entry { id, path: path.to_path_buf(), existed });
+
+    // Drop the oldest snapshots beyond the configured depth
+    let excess = log.len().saturating_sub(depth);
+    for old in log.drain(..excess) {
+        let _ = fs::remove_file(snapshot_path(&dir, old.id));
+    }
+
+    save_log(&dir, &log)
+}
+
+/// Revert the most recent recorded mutation
+pub fn undo_last(root: &Path) -> Result<String> {
+    let dir = root.join(UNDO_DIR);
+    let mut log = load_log(&dir);
+    let Some(entry) = log.pop() else {
+        return Ok("Nothing to undo".to_string());
+    };
+
+    let snapshot = snapshot_path(&dir, entry.id);
+    let message = if entry.existed {
+        fs::copy(&snapshot, &entry.path)
+            .with_context(|| format!("restoring {}", entry.path.display()))?;
+        let _ = fs::remove_file(&snapshot);
+        format!("Restored previous contents of {}", entry.path.display())
+    } else {
+        if entry.path.exists() {
+            fs::remove_file(&entry.path)
+                .with_context(|| format!("removing {}", entry.path.display()))?;
+        }
+        format!("Removed {} (it did not exist before)", entry.path.display())
+    };
+
+    save_log(&dir, &log)?;
+    Ok(message)
+}
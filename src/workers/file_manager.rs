@@ -1,10 +1,21 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use anyhow::Result;
 use async_trait::async_trait;
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use regex::{NoExpand, Regex};
 use reqwest::Client;
 use serde_json::json;
-use crate::traits::{Worker, WorkerFactory, Agent, Tool, ToolFunction};
+use crate::{blocking, budget, config, file_lock, request_id, scratch, shutdown, undo};
+use crate::workspace::WorkspaceRoot;
+use super::limits::run_limited;
+use super::normalize;
+use crate::traits::{Capabilities, Worker, WorkerFactory, Agent, RunOptions, Tool, ToolFunction};
 
 inventory::submit! {
     WorkerFactory(|| Box::new(FileManager::new(None)))
@@ -12,6 +23,8 @@ inventory::submit! {
 
 pub struct FileManager {
     base: PathBuf,
+    /// Mutating tools may only touch paths under this root (defaults to `base`)
+    write_root: PathBuf,
 }
 
 #[async_trait]
@@ -24,9 +37,23 @@ impl Worker for FileManager {
         "Manages file system operations including reading, writing, and organizing files"
     }
 
-    async fn process(&self, instruction: &str) -> Result<String> {
+    async fn process(&self, instruction: &str, options: &RunOptions) -> Result<String> {
         // Delegate to Agent's run method
-        Agent::run(self, instruction).await
+        Agent::run(self, &self.preprocess_instruction(instruction), options)
+            .await
+            .map(|result| self.postprocess_output(result))
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        Agent::get_tools(self)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { reads: true, writes: true, shell: true, ..Capabilities::default() }
+    }
+
+    fn model_name(&self) -> Option<&'static str> {
+        Some(Agent::model(self))
     }
 }
 
@@ -47,19 +74,31 @@ impl Agent for FileManager {
         Client::new()
     }
 
+    fn config_name(&self) -> &'static str {
+        Worker::role(self)
+    }
+
     fn get_tools(&self) -> Vec<Tool> {
-        vec![
+        let tools = vec![
             Tool {
                 tool_type: "function".to_string(),
                 function: ToolFunction {
                     name: "read_file".to_string(),
-                    description: "Read the contents of a file".to_string(),
+                    description: "Read the contents of a file (.gz and .bz2 files are decompressed, non-UTF-8 text is converted)".to_string(),
                     parameters: json!({
                         "type": "object",
                         "properties": {
                             "path": {
                                 "type": "string",
                                 "description": "Path to the file to read"
+                            },
+                            "encoding": {
+                                "type": "string",
+                                "description": "Source encoding (e.g. latin1, shift_jis, utf-16le); detected when omitted"
+                            },
+                            "numbered": {
+                                "type": "boolean",
+                                "description": "Prefix each line with its 1-indexed line number, for line-based edits (default false)"
                             }
                         },
                         "required": ["path"]
@@ -138,94 +177,2196 @@ impl Agent for FileManager {
                     }),
                 },
             },
-        ]
-    }
-
-    fn execute_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
-        match name {
-            "read_file" => {
-                let path = args["path"].as_str().unwrap_or("");
-                let full_path = self.base.join(path);
-                match fs::read_to_string(&full_path) {
-                    Ok(content) => Ok(content),
-                    Err(e) => Ok(format!("Error reading file: {}", e)),
-                }
-            }
-            "write_file" => {
-                let path = args["path"].as_str().unwrap_or("");
-                let content = args["content"].as_str().unwrap_or("");
-                let full_path = self.base.join(path);
-                match fs::write(&full_path, content) {
-                    Ok(_) => Ok(format!("Successfully wrote to {}", path)),
-                    Err(e) => Ok(format!("Error writing file: {}", e)),
-                }
-            }
-            "list_directory" => {
-                let path = args["path"].as_str().unwrap_or(".");
-                let full_path = self.base.join(path);
-                match fs::read_dir(&full_path) {
-                    Ok(entries) => {
-                        let files: Vec<String> = entries
-                            .filter_map(|e| e.ok())
-                            .map(|e| e.file_name().to_string_lossy().to_string())
-                            .collect();
-                        Ok(json!(files).to_string())
-                    }
-                    Err(e) => Ok(format!("Error listing directory: {}", e)),
-                }
-            }
-            "delete_file" => {
-                let path = args["path"].as_str().unwrap_or("");
-                let full_path = self.base.join(path);
-                match fs::remove_file(&full_path) {
-                    Ok(_) => Ok(format!("Successfully deleted {}", path)),
-                    Err(e) => Ok(format!("Error deleting file: {}", e)),
-                }
-            }
-            "create_directory" => {
-                let path = args["path"].as_str().unwrap_or("");
-                let full_path = self.base.join(path);
-                match fs::create_dir_all(&full_path) {
-                    Ok(_) => Ok(format!("Successfully created directory {}", path)),
-                    Err(e) => Ok(format!("Error creating directory: {}", e)),
-                }
-            }
-            _ => Ok(format!("Unknown tool: {}", name)),
-        }
-    }
-}
-
-impl FileManager {
-    pub fn new(path: Option<&str>) -> Self {
-        let base = match path {
-            Some(p) => PathBuf::from(p),
-            None => PathBuf::from("."),
-        };
-        FileManager { base }
-    }
-}
-
-const SYSTEM_PROMPT: &str = r#"You are FileManager, a specialized Worker in the Hive system focused on file operations.
-
-IMPORTANT: Always respond in English.
-
-# Your Role
-You receive file-related tasks from the Queen and execute them using your available tools. You MUST use your tools to complete tasks - do not claim you cannot access files.
-
-# How to Work
-1. When asked to read a file, USE the read_file tool
-2. When asked to write a file, USE the write_file tool
-3. When asked to list files, USE the list_directory tool
-4. Always use tools first, then report results
-
-# Operational Guidelines
-- Use your tools to complete tasks
-- Handle errors gracefully and report them clearly
-- For complex tasks, break them into multiple tool calls
-- Be explicit about what succeeded vs. failed
-
-# Constraints
-- Stay focused on file operations
-- Always respond in English
-
-You are efficient, reliable, and always use your tools to complete tasks."#;
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "check_code".to_string(),
+                    description: "Run the configured linter (cargo clippy by default) and return diagnostics as JSON".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Project directory to check (defaults to the working directory)"
+                            }
+                        },
+                        "required": []
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "read_files".to_string(),
+                    description: "Read several files in one call; returns a JSON map of path to content".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "paths": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                                "description": "Paths of the files to read"
+                            }
+                        },
+                        "required": ["paths"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "batch".to_string(),
+                    description: "Run several file operations in order, stopping at the first failure".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "operations": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "op": { "type": "string", "description": "Tool name, e.g. write_file" },
+                                        "args": { "type": "object", "description": "Arguments for that tool" }
+                                    },
+                                    "required": ["op", "args"]
+                                },
+                                "description": "Ordered list of operations to execute"
+                            }
+                        },
+                        "required": ["operations"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "undo_last".to_string(),
+                    description: "Undo the most recent file write or delete".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {},
+                        "required": []
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "render_template".to_string(),
+                    description: "Fill {{var}} placeholders in a template file and write the result to a destination".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "template": {
+                                "type": "string",
+                                "description": "Path to the template file"
+                            },
+                            "destination": {
+                                "type": "string",
+                                "description": "Path to write the rendered file to"
+                            },
+                            "vars": {
+                                "type": "object",
+                                "description": "Values for each placeholder, keyed by variable name"
+                            }
+                        },
+                        "required": ["template", "destination", "vars"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "read_lines".to_string(),
+                    description: "Read a range of lines from a file (1-indexed, inclusive)".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to read"
+                            },
+                            "start_line": {
+                                "type": "integer",
+                                "description": "First line to return"
+                            },
+                            "end_line": {
+                                "type": "integer",
+                                "description": "Last line to return (defaults to start_line + 99)"
+                            },
+                            "numbered": {
+                                "type": "boolean",
+                                "description": "Prefix each line with its line number (default false)"
+                            }
+                        },
+                        "required": ["path", "start_line"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "rename_in_tree".to_string(),
+                    description: "Rename an identifier (whole-word matches) across all files matching a glob; returns per-file counts and a diff".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "from": {
+                                "type": "string",
+                                "description": "Identifier to rename"
+                            },
+                            "to": {
+                                "type": "string",
+                                "description": "New identifier"
+                            },
+                            "glob": {
+                                "type": "string",
+                                "description": "Files to search, relative to the working directory (e.g. src/**/*.rs)"
+                            },
+                            "dry_run": {
+                                "type": "boolean",
+                                "description": "Preview the changes without writing (default false)"
+                            }
+                        },
+                        "required": ["from", "to", "glob"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "diff_files".to_string(),
+                    description: "Compare two files and return a unified diff".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path_a": {
+                                "type": "string",
+                                "description": "Path to the original file"
+                            },
+                            "path_b": {
+                                "type": "string",
+                                "description": "Path to the file to compare against it"
+                            },
+                            "context": {
+                                "type": "integer",
+                                "description": "Unchanged lines to show around each change (default 3)"
+                            }
+                        },
+                        "required": ["path_a", "path_b"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "directory_tree".to_string(),
+                    description: "Show a directory as an indented tree (directories end with /), skipping .hiveignore matches".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Directory to start from (defaults to the working directory)"
+                            },
+                            "max_depth": {
+                                "type": "integer",
+                                "description": "How many levels to descend (default 3)"
+                            },
+                            "max_entries": {
+                                "type": "integer",
+                                "description": "Stop after this many entries (default 200)"
+                            }
+                        },
+                        "required": []
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "read_file_page".to_string(),
+                    description: "Read one page of lines from a large file, with page metadata".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to read"
+                            },
+                            "page": {
+                                "type": "integer",
+                                "description": "Page number, starting at 1"
+                            },
+                            "page_size": {
+                                "type": "integer",
+                                "description": "Lines per page (default 200)"
+                            }
+                        },
+                        "required": ["path", "page"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "set_permissions".to_string(),
+                    description: "Set a file's Unix permission bits, e.g. mode \"755\" to make a script executable".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file"
+                            },
+                            "mode": {
+                                "type": "string",
+                                "description": "Octal permission bits such as 755 or 0644"
+                            }
+                        },
+                        "required": ["path", "mode"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "get_env".to_string(),
+                    description: "Read an environment variable; only allowlisted names are readable".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "name": {
+                                "type": "string",
+                                "description": "Variable name, e.g. PATH or CARGO_HOME"
+                            }
+                        },
+                        "required": ["name"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "tail_follow".to_string(),
+                    description: "Watch a file for newly appended lines (like tail -f) for a bounded time and return them".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to watch"
+                            },
+                            "duration_secs": {
+                                "type": "integer",
+                                "description": "How long to watch, in seconds (default 10, at most 60)"
+                            },
+                            "match": {
+                                "type": "string",
+                                "description": "Only keep lines matching this regex"
+                            }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "write_files".to_string(),
+                    description: "Write several files in one call, creating parent directories as needed".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "files": {
+                                "type": "array",
+                                "items": {
+                                    "type": "object",
+                                    "properties": {
+                                        "path": { "type": "string", "description": "Path to the file" },
+                                        "content": { "type": "string", "description": "Content to write" }
+                                    },
+                                    "required": ["path", "content"]
+                                },
+                                "description": "Files to write, in order"
+                            },
+                            "stop_on_error": {
+                                "type": "boolean",
+                                "description": "Stop at the first failed write (default true); false writes the rest anyway"
+                            }
+                        },
+                        "required": ["files"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "parse_structured".to_string(),
+                    description: "Validate a JSON, YAML or TOML file and return it pretty-printed, or the parse error with line and column".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file"
+                            },
+                            "format": {
+                                "type": "string",
+                                "enum": ["json", "yaml", "toml"],
+                                "description": "File format; detected from the extension when omitted"
+                            }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "read_csv".to_string(),
+                    description: "Read rows of a CSV (or TSV) file as JSON objects keyed by the header row, with the file's total row count".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the CSV file"
+                            },
+                            "columns": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                                "description": "Only return these columns (default all)"
+                            },
+                            "start_row": {
+                                "type": "integer",
+                                "description": "Data row to start from, 0-based, not counting the header (default 0)"
+                            },
+                            "max_rows": {
+                                "type": "integer",
+                                "description": "Most rows to return (default 100)"
+                            }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "read_link".to_string(),
+                    description: "Show where a symlink points, and whether that target is inside the working directory".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the symlink"
+                            }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "convert_encoding".to_string(),
+                    description: "Rewrite a text file in another encoding".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file"
+                            },
+                            "to": {
+                                "type": "string",
+                                "description": "Target encoding (e.g. utf-8, latin1, shift_jis, utf-16le)"
+                            },
+                            "from": {
+                                "type": "string",
+                                "description": "Current encoding; detected when omitted"
+                            }
+                        },
+                        "required": ["path", "to"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "scratch_file".to_string(),
+                    description: "Create an empty, uniquely named scratch file for intermediate work and return its path; scratch files are deleted when the session ends".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "extension": {
+                                "type": "string",
+                                "description": "File extension without the dot, e.g. py or json (optional)"
+                            }
+                        },
+                        "required": []
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "recent_files".to_string(),
+                    description: "List files under a directory by modification time, newest first, as JSON [{path, modified, size}] (modified in Unix seconds); skips .hiveignore matches".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Directory to search (default: the working directory)"
+                            },
+                            "minutes": {
+                                "type": "integer",
+                                "description": "Only files modified within this many minutes (optional)"
+                            },
+                            "limit": {
+                                "type": "integer",
+                                "description": "Most files to return (default 20)"
+                            }
+                        },
+                        "required": []
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "insert_at_line".to_string(),
+                    description: "Insert content into a file at a line (1-indexed) and report the line numbers it now occupies. By default it goes before `line`, so it starts at `line` (line count + 1 appends). With after=true it goes after `line` (0 inserts at the top, the line count appends)".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to edit"
+                            },
+                            "line": {
+                                "type": "integer",
+                                "description": "Line to insert before (or after, with after=true)"
+                            },
+                            "content": {
+                                "type": "string",
+                                "description": "Text to insert"
+                            },
+                            "after": {
+                                "type": "boolean",
+                                "description": "Insert after `line` instead of before it (default false)"
+                            }
+                        },
+                        "required": ["path", "line", "content"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "replace_lines".to_string(),
+                    description: "Replace a range of lines in a file (1-indexed, inclusive) with new content, which may span any number of lines; empty content deletes the range".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to edit"
+                            },
+                            "start_line": {
+                                "type": "integer",
+                                "description": "First line to replace"
+                            },
+                            "end_line": {
+                                "type": "integer",
+                                "description": "Last line to replace (defaults to start_line)"
+                            },
+                            "content": {
+                                "type": "string",
+                                "description": "Replacement text"
+                            }
+                        },
+                        "required": ["path", "start_line", "content"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "reindent".to_string(),
+                    description: "Normalize the leading whitespace of a range of lines (1-indexed, inclusive) to spaces or tabs, keeping each line's nesting level; nothing else on the line changes".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file to edit"
+                            },
+                            "start_line": {
+                                "type": "integer",
+                                "description": "First line to reindent (default 1)"
+                            },
+                            "end_line": {
+                                "type": "integer",
+                                "description": "Last line to reindent (default: the end of the file)"
+                            },
+                            "style": {
+                                "type": "string",
+                                "enum": ["spaces", "tabs"],
+                                "description": "Indent with spaces (default) or tabs"
+                            },
+                            "width": {
+                                "type": "integer",
+                                "description": "Spaces per level, and the columns a tab counts for (default 4)"
+                            }
+                        },
+                        "required": ["path"]
+                    }),
+                },
+            },
+        ];
+
+        // Only expose the tools this deployment enables for the file manager
+        tools
+            .into_iter()
+            .filter(|tool| config::get().tool_enabled(self.role(), &tool.function.name))
+            .filter(|tool| tool.function.name != "set_permissions" || config::get().allow_set_permissions)
+            .collect()
+    }
+
+    fn has_side_effects(&self, name: &str) -> bool {
+        matches!(
+            name,
+            "write_file"
+                | "write_files"
+                | "delete_file"
+                | "create_directory"
+                | "batch"
+                | "undo_last"
+                | "render_template"
+                | "rename_in_tree"
+                | "set_permissions"
+                | "convert_encoding"
+                | "scratch_file"
+                | "insert_at_line"
+                | "replace_lines"
+                | "reindent"
+                // Runs a build, which writes target/ and executes build scripts
+                | "check_code"
+        )
+    }
+
+    fn execute_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
+        if !config::get().tool_enabled(self.role(), name) {
+            return Ok(format!("Error: tool '{}' is disabled for {}", name, self.role()));
+        }
+
+        match name {
+            "read_file" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let full_path = match self.resolve_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                let encoding = match args["encoding"].as_str().map(|label| (label, Encoding::for_label(label.as_bytes()))) {
+                    None => None,
+                    Some((_, Some(encoding))) => Some(encoding),
+                    Some((label, None)) => return Ok(format!("Error: unknown encoding '{}'", label)),
+                };
+                let numbered = args["numbered"].as_bool().unwrap_or(false);
+                let budget = config::get().max_result_bytes;
+                if let Some(decompressed) = read_decompressed(&full_path, budget) {
+                    return Ok(match decompressed {
+                        Ok((bytes, false)) => decode_text(path, bytes, encoding, numbered),
+                        Ok((bytes, true)) => format!(
+                            "{}\n[truncated: decompressed content exceeds the {} byte limit]",
+                            decode_text(path, bytes, encoding, numbered),
+                            budget
+                        ),
+                        Err(e) => format!("Error decompressing file: {}", e),
+                    });
+                }
+                if let Ok(meta) = fs::metadata(&full_path)
+                    && meta.len() > budget as u64
+                {
+                    return Ok(format!(
+                        "File {} is {} bytes, over the {} byte limit for read_file; use read_file_page or read_lines instead",
+                        path,
+                        meta.len(),
+                        budget
+                    ));
+                }
+                match fs::read(&full_path) {
+                    Ok(bytes) => Ok(decode_text(path, bytes, encoding, numbered)),
+                    Err(e) => Ok(format!("Error reading file: {}", e)),
+                }
+            }
+            "write_file" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let content = args["content"].as_str().unwrap_or("");
+                let full_path = match self.resolve_write_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                let _guard = file_lock::lock(&full_path);
+                if unchanged(&full_path, content) {
+                    return Ok(format!("No change needed: {} already has this content", path));
+                }
+                if let Err(e) = undo::record(&self.write_root, &full_path) {
+                    return Ok(format!("Error writing file: {}", e));
+                }
+                match fs::write(&full_path, content) {
+                    Ok(_) => Ok(format!("Successfully wrote to {}", path)),
+                    Err(e) => Ok(format!("Error writing file: {}", e)),
+                }
+            }
+            "list_directory" => {
+                let path = args["path"].as_str().unwrap_or(".");
+                let full_path = match self.resolve_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                match fs::read_dir(&full_path) {
+                    Ok(entries) => {
+                        let files: Vec<String> = entries
+                            .filter_map(|e| e.ok())
+                            .map(|e| {
+                                let name = e.file_name().to_string_lossy().to_string();
+                                match fs::read_link(e.path()) {
+                                    Ok(target) => format!("{} -> {}", name, target.display()),
+                                    Err(_) => name,
+                                }
+                            })
+                            .collect();
+                        let listing = json!(files).to_string();
+                        let budget = config::get().max_result_bytes;
+                        if listing.len() > budget {
+                            return Ok(format!(
+                                "Directory {} has {} entries ({} bytes), over the {} byte limit; list a subdirectory instead",
+                                path,
+                                files.len(),
+                                listing.len(),
+                                budget
+                            ));
+                        }
+                        Ok(listing)
+                    }
+                    Err(e) => Ok(format!("Error listing directory: {}", e)),
+                }
+            }
+            "delete_file" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let full_path = match self.resolve_write_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                let _guard = file_lock::lock(&full_path);
+                if full_path.is_file()
+                    && let Err(e) = undo::record(&self.write_root, &full_path)
+                {
+                    return Ok(format!("Error deleting file: {}", e));
+                }
+                match fs::remove_file(&full_path) {
+                    Ok(_) => Ok(format!("Successfully deleted {}", path)),
+                    Err(e) => Ok(format!("Error deleting file: {}", e)),
+                }
+            }
+            "create_directory" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let full_path = match self.resolve_write_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                match fs::create_dir_all(&full_path) {
+                    Ok(_) => Ok(format!("Successfully created directory {}", path)),
+                    Err(e) => Ok(format!("Error creating directory: {}", e)),
+                }
+            }
+            "check_code" => {
+                let path = args["path"].as_str().unwrap_or(".");
+                let full_path = match self.resolve_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                Ok(self.check_code(&full_path))
+            }
+            "read_files" => {
+                let paths: Vec<&str> = args["paths"]
+                    .as_array()
+                    .map(|items| items.iter().filter_map(|p| p.as_str()).collect())
+                    .unwrap_or_default();
+                Ok(self.read_files(&paths))
+            }
+            "batch" => {
+                let operations = args["operations"].as_array().cloned().unwrap_or_default();
+                self.batch(&operations)
+            }
+            "undo_last" => match undo::undo_last(&self.write_root) {
+                Ok(message) => Ok(message),
+                Err(e) => Ok(format!("Error undoing last change: {}", e)),
+            },
+            "render_template" => {
+                let template = args["template"].as_str().unwrap_or("");
+                let destination = args["destination"].as_str().unwrap_or("");
+                Ok(self.render_template(template, destination, &args["vars"]))
+            }
+            "read_lines" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let start = args["start_line"].as_u64().unwrap_or(1) as usize;
+                let end = args["end_line"].as_u64().map(|n| n as usize).unwrap_or(start.saturating_add(99));
+                if start == 0 || end < start {
+                    return Ok(format!("Error: invalid line range {}-{}", start, end));
+                }
+                let full_path = match self.resolve_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                match fs::read_to_string(&full_path) {
+                    Ok(content) => {
+                        let total = content.lines().count();
+                        if start > total {
+                            return Ok(format!("Error: {} has only {} lines", path, total));
+                        }
+                        let lines: Vec<&str> = content.lines().skip(start - 1).take(end - start + 1).collect();
+                        if args["numbered"].as_bool().unwrap_or(false) {
+                            Ok(number_lines(&lines.join("\n"), start))
+                        } else {
+                            Ok(lines.join("\n"))
+                        }
+                    }
+                    Err(e) => Ok(format!("Error reading file: {}", e)),
+                }
+            }
+            "rename_in_tree" => {
+                let from = args["from"].as_str().unwrap_or("");
+                let to = args["to"].as_str().unwrap_or("");
+                let pattern = args["glob"].as_str().unwrap_or("");
+                let dry_run = args["dry_run"].as_bool().unwrap_or(false);
+                Ok(self.rename_in_tree(from, to, pattern, dry_run))
+            }
+            "diff_files" => {
+                let path_a = args["path_a"].as_str().unwrap_or("");
+                let path_b = args["path_b"].as_str().unwrap_or("");
+                let context = args["context"].as_u64().unwrap_or(3) as usize;
+                Ok(self.diff_files(path_a, path_b, context))
+            }
+            "directory_tree" => {
+                let path = args["path"].as_str().unwrap_or(".");
+                let max_depth = args["max_depth"].as_u64().unwrap_or(3) as usize;
+                let max_entries = args["max_entries"].as_u64().unwrap_or(200) as usize;
+                let full_path = match self.resolve_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                if !full_path.is_dir() {
+                    return Ok(format!("Error: {} is not a directory", path));
+                }
+
+                let ignore = self.ignore_patterns();
+                let mut walk = TreeWalk {
+                    ignore: &ignore,
+                    remaining: max_entries,
+                    truncated: false,
+                    lines: vec![format!("{}/", path.trim_end_matches('/'))],
+                };
+                self.render_tree(&mut walk, &full_path, "", max_depth);
+                if walk.truncated {
+                    walk.lines.push(format!("... (truncated at {} entries)", max_entries));
+                }
+                Ok(walk.lines.join("\n"))
+            }
+            "read_file_page" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let page = args["page"].as_u64().unwrap_or(1).max(1) as usize;
+                let page_size = args["page_size"].as_u64().unwrap_or(200).max(1) as usize;
+                let full_path = match self.resolve_path(path) {
+                    Ok(full_path) => full_path,
+                    Err(e) => return Ok(e),
+                };
+                let content = match fs::read_to_string(&full_path) {
+                    Ok(content) => content,
+                    Err(e) => return Ok(format!("Error reading file: {}", e)),
+                };
+
+                let lines: Vec<&str> = content.lines().collect();
+                let total_pages = lines.len().div_ceil(page_size).max(1);
+                if page > total_pages {
+                    return Ok(format!("Error: page {} is past the end ({} pages)", page, total_pages));
+                }
+                let start = (page - 1) * page_size;
+                let end = (start + page_size).min(lines.len());
+                Ok(json!({
+                    "page": page,
+                    "total_pages": total_pages,
+                    "total_lines": lines.len(),
+                    "content": lines[start..end].join("\n"),
+                })
+                .to_string())
+            }
+            "set_permissions" => {
+                if !config::get().allow_set_permissions {
+                    return Ok("Error: set_permissions is disabled (set HIVE_ALLOW_SET_PERMISSIONS=1 to enable)".to_string());
+                }
+                let path = args["path"].as_str().unwrap_or("");
+                let mode = args["mode"].as_str().unwrap_or("");
+                Ok(self.set_permissions(path, mode))
+            }
+            "get_env" => {
+                let name = args["name"].as_str().unwrap_or("");
+                if !config::get().env_allowlist.iter().any(|allowed| allowed == name) {
+                    return Ok(format!(
+                        "Error: environment variable '{}' is not in the allowlist ({})",
+                        name,
+                        config::get().env_allowlist.join(", ")
+                    ));
+                }
+                match std::env::var_os(name) {
+                    Some(value) => Ok(value.to_string_lossy().into_owned()),
+                    None => Ok(format!("{} is not set", name)),
+                }
+            }
+            "tail_follow" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let duration = args["duration_secs"].as_u64().unwrap_or(10).min(MAX_TAIL_SECS);
+                Ok(self.tail_follow(path, Duration::from_secs(duration), args["match"].as_str()))
+            }
+            "write_files" => {
+                let files = args["files"].as_array().map(Vec::as_slice).unwrap_or_default();
+                let stop_on_error = args["stop_on_error"].as_bool().unwrap_or(true);
+                self.write_files(files, stop_on_error)
+            }
+            "parse_structured" => {
+                let path = args["path"].as_str().unwrap_or("");
+                Ok(self.parse_structured(path, args["format"].as_str()))
+            }
+            "read_csv" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let columns: Option<Vec<&str>> = args["columns"]
+                    .as_array()
+                    .map(|columns| columns.iter().filter_map(|c| c.as_str()).collect());
+                let start_row = args["start_row"].as_u64().unwrap_or(0) as usize;
+                let max_rows = args["max_rows"].as_u64().unwrap_or(DEFAULT_CSV_ROWS) as usize;
+                Ok(self.read_csv(path, columns.as_deref(), start_row, max_rows))
+            }
+            "read_link" => {
+                let path = args["path"].as_str().unwrap_or("");
+                Ok(self.read_link(path))
+            }
+            "convert_encoding" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let to = args["to"].as_str().unwrap_or("");
+                Ok(self.convert_encoding(path, args["from"].as_str(), to))
+            }
+            "scratch_file" => Ok(self.scratch_file(args["extension"].as_str())),
+            "recent_files" => {
+                let path = args["path"].as_str().unwrap_or(".");
+                let minutes = args["minutes"].as_u64();
+                let limit = args["limit"].as_u64().unwrap_or(DEFAULT_RECENT_FILES).max(1) as usize;
+                Ok(self.recent_files(path, minutes, limit))
+            }
+            "insert_at_line" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let line = args["line"].as_u64().unwrap_or(0) as usize;
+                let content = args["content"].as_str().unwrap_or("");
+                let after = args["after"].as_bool().unwrap_or(false);
+                Ok(self.insert_at_line(path, line, content, after))
+            }
+            "replace_lines" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let start = args["start_line"].as_u64().unwrap_or(0) as usize;
+                let end = args["end_line"].as_u64().map(|n| n as usize).unwrap_or(start);
+                let content = args["content"].as_str().unwrap_or("");
+                Ok(self.replace_lines(path, start, end, content))
+            }
+            "reindent" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let start = args["start_line"].as_u64().unwrap_or(1) as usize;
+                let end = args["end_line"].as_u64().map(|n| n as usize);
+                let tabs = args["style"].as_str() == Some("tabs");
+                let width = args["width"].as_u64().unwrap_or(4).clamp(1, 16) as usize;
+                Ok(self.reindent(path, start, end, tabs, width))
+            }
+            _ => Ok(format!("Unknown tool: {}", name)),
+        }
+    }
+}
+
+impl FileManager {
+    pub fn new(path: Option<&str>) -> Self {
+        let base = match path {
+            // Canonical base so sandbox checks compare absolute paths
+            Some(p) => fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p)),
+            None => WorkspaceRoot::shared().worker_base("file_manager"),
+        };
+        let write_root = match &config::get().write_root {
+            Some(root) => normalize(&base.join(root)),
+            None => base.clone(),
+        };
+        FileManager { base, write_root }
+    }
+
+    /// Join a model-supplied path onto the base. Paths are meant to be relative, so an absolute one
+    /// is logged and accepted only if it already points inside the base
+    fn join_base(&self, path: &str) -> Result<PathBuf, String> {
+        if !Path::new(path).is_absolute() {
+            return Ok(normalize(&self.base.join(path)));
+        }
+        let resolved = normalize(Path::new(path));
+        match resolved.strip_prefix(&self.base) {
+            Ok(relative) => {
+                eprintln!("[DEBUG{}] Absolute path '{}' rewritten as '{}'", request_id::tag(), path, relative.display());
+                Ok(resolved)
+            }
+            Err(_) => {
+                eprintln!("[DEBUG{}] Refused absolute path '{}' outside {}", request_id::tag(), path, self.base.display());
+                Err(format!(
+                    "Error: '{}' is an absolute path outside the working directory; use a path relative to the working directory instead (e.g. 'src/main.rs')",
+                    path
+                ))
+            }
+        }
+    }
+
+    /// Resolve a model-supplied path against the base, refusing anything that escapes it
+    fn resolve_path(&self, path: &str) -> Result<PathBuf, String> {
+        let resolved = self.join_base(path)?;
+        if !resolved.starts_with(&self.base) {
+            Err(format!("Error: path '{}' is outside the working directory", path))
+        } else if self.escapes_via_symlink(&resolved) {
+            Err(format!("Error: path '{}' leads outside the working directory through a symlink", path))
+        } else {
+            Ok(resolved)
+        }
+    }
+
+    /// Like `resolve_path`, but the result must also fall under the write root
+    fn resolve_write_path(&self, path: &str) -> Result<PathBuf, String> {
+        let resolved = self.join_base(path)?;
+        if resolved.starts_with(&self.write_root) {
+            if self.escapes_via_symlink(&resolved) {
+                return Err(format!("Error: path '{}' leads outside the working directory through a symlink", path));
+            }
+            Ok(resolved)
+        } else {
+            Err(format!(
+                "Error: path '{}' is outside the writable directory '{}'",
+                path,
+                self.write_root.strip_prefix(&self.base).unwrap_or(&self.write_root).display()
+            ))
+        }
+    }
+
+    /// Whether following symlinks in `resolved` (a lexically checked path) lands outside the base
+    /// The path may not exist yet, so its deepest existing ancestor is what gets resolved
+    fn escapes_via_symlink(&self, resolved: &Path) -> bool {
+        if config::get().follow_symlinks {
+            return false;
+        }
+        let Some(existing) = resolved.ancestors().find(|p| p.symlink_metadata().is_ok()) else {
+            return false;
+        };
+        match fs::canonicalize(existing) {
+            Ok(real) => !real.starts_with(&self.base),
+            // A dangling link could still be written through to wherever it points
+            Err(_) => true,
+        }
+    }
+
+    /// Run the configured check command and collect its diagnostics as `{level, file, line, message}`
+    fn check_code(&self, dir: &Path) -> String {
+        let config = config::get();
+        let command = &config.check_command;
+        let mut parts = command.split_whitespace();
+        let Some(program) = parts.next() else {
+            return "Error: no check command configured".to_string();
+        };
+
+        let timeout = (config.check_timeout_secs > 0).then(|| Duration::from_secs(config.check_timeout_secs));
+        let args: Vec<&OsStr> = std::iter::once(program).chain(parts).map(OsStr::new).collect();
+        let output = match blocking::block_in_place(|| run_limited(dir, r#"exec "$0" "$@""#, &args, timeout)) {
+            Ok(output) => output,
+            Err(e) => return format!("Error running check command: {}", e),
+        };
+        // sh reports a program it can't find with exit status 127
+        if output.status.code() == Some(127) {
+            return format!("Error: '{}' is not installed or not on PATH (check command: {})", program, command);
+        }
+        if let Some(limit) = output.limit_exceeded() {
+            return format!("Error: check command was killed after exceeding the {}", limit);
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let diagnostics: Vec<serde_json::Value> = stdout
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .filter(|entry| entry["reason"] == "compiler-message")
+            .filter_map(|entry| {
+                let message = &entry["message"];
+                let level = message["level"].as_str()?;
+                // Skip the trailing "N warnings emitted" summaries, which carry no location
+                let span = message["spans"]
+                    .as_array()?
+                    .iter()
+                    .find(|s| s["is_primary"].as_bool().unwrap_or(false))?;
+                Some(json!({
+                    "level": level,
+                    "file": span["file_name"],
+                    "line": span["line_start"],
+                    "message": message["message"],
+                }))
+            })
+            .collect();
+
+        if diagnostics.is_empty() && !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return format!("Check command failed ({}): {}", output.status, stderr.trim());
+        }
+
+        json!(diagnostics).to_string()
+    }
+
+    /// Read several files into one JSON object, truncating or skipping once the byte budget is spent
+    fn read_files(&self, paths: &[&str]) -> String {
+        let mut budget = config::get().max_read_bytes;
+        let mut files = serde_json::Map::new();
+        let mut errors = serde_json::Map::new();
+        let mut truncated = Vec::new();
+        let mut skipped = Vec::new();
+
+        for path in paths {
+            if budget == 0 {
+                skipped.push(*path);
+                continue;
+            }
+
+            let content = self
+                .resolve_path(path)
+                .and_then(|full_path| fs::read_to_string(&full_path).map_err(|e| format!("Error reading file: {}", e)));
+            match content {
+                Ok(content) if content.len() > budget => {
+                    let cut = content.floor_char_boundary(budget);
+                    files.insert(path.to_string(), json!(&content[..cut]));
+                    truncated.push(*path);
+                    budget = 0;
+                }
+                Ok(content) => {
+                    budget -= content.len();
+                    files.insert(path.to_string(), json!(content));
+                }
+                Err(e) => {
+                    errors.insert(path.to_string(), json!(e));
+                }
+            }
+        }
+
+        json!({
+            "files": files,
+            "errors": errors,
+            "truncated": truncated,
+            "skipped": skipped,
+        })
+        .to_string()
+    }
+
+    /// Validate every operation up front, then run them in order until one fails
+    fn batch(&self, operations: &[serde_json::Value]) -> Result<String> {
+        let max_ops = config::get().max_batch_ops;
+        if operations.len() > max_ops {
+            return Ok(format!("Error: batch has {} operations; the limit is {}", operations.len(), max_ops));
+        }
+
+        let tools = Agent::get_tools(self);
+        for (index, operation) in operations.iter().enumerate() {
+            let op = operation["op"].as_str().unwrap_or("");
+            let validation = match tools.iter().find(|t| t.function.name == op && op != "batch") {
+                Some(tool) => tool.function.validate(&operation["args"]),
+                None => Err("unknown operation".to_string()),
+            };
+            if let Err(e) = validation {
+                return Ok(format!("Error: operation {} ('{}') is invalid: {}; nothing was executed", index, op, e));
+            }
+        }
+
+        let mut results = Vec::new();
+        let mut status = "ok";
+        for operation in operations {
+            let op = operation["op"].as_str().unwrap_or("");
+            let result = self.execute_tool(op, &operation["args"])?;
+            let failed = is_error(&result);
+            results.push(json!({ "op": op, "ok": !failed, "result": result }));
+            if failed {
+                status = "failed";
+                break;
+            }
+        }
+
+        Ok(json!({
+            "status": status,
+            "completed": results.len(),
+            "total": operations.len(),
+            "results": results,
+        })
+        .to_string())
+    }
+
+    /// Write each `{path, content}` entry through `write_file`, creating parent directories first
+    fn write_files(&self, files: &[serde_json::Value], stop_on_error: bool) -> Result<String> {
+        let max_ops = config::get().max_batch_ops;
+        if files.len() > max_ops {
+            return Ok(format!("Error: {} files requested; the limit per call is {}", files.len(), max_ops));
+        }
+
+        let mut results = Vec::new();
+        let mut failures = 0;
+        for file in files {
+            let path = file["path"].as_str().unwrap_or("");
+            let result = match self.resolve_write_path(path) {
+                Ok(full_path) => match full_path.parent().map(fs::create_dir_all).transpose() {
+                    Ok(_) => self.execute_tool("write_file", file)?,
+                    Err(e) => format!("Error creating parent directory: {}", e),
+                },
+                Err(e) => e,
+            };
+            let failed = is_error(&result);
+            results.push(json!({ "path": path, "ok": !failed, "result": result }));
+            if failed {
+                failures += 1;
+                if stop_on_error {
+                    break;
+                }
+            }
+        }
+
+        Ok(json!({
+            "status": if failures == 0 { "ok" } else { "failed" },
+            "written": results.len() - failures,
+            "total": files.len(),
+            "results": results,
+        })
+        .to_string())
+    }
+
+    /// Parse a config file and return it normalized, or where and why it fails to parse
+    fn parse_structured(&self, path: &str, format: Option<&str>) -> String {
+        let full_path = match self.resolve_path(path) {
+            Ok(full_path) => full_path,
+            Err(e) => return e,
+        };
+        let extension = full_path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let format = match format.or(extension.as_deref()) {
+            Some("json") => "json",
+            Some("yaml" | "yml") => "yaml",
+            Some("toml") => "toml",
+            _ => return format!("Error: can't tell the format of {}; pass format json, yaml or toml", path),
+        };
+        let text = match fs::read_to_string(&full_path) {
+            Ok(text) => text,
+            Err(e) => return format!("Error reading file: {}", e),
+        };
+
+        // (line, column, message) on failure, both 1-based
+        let parsed: Result<String, (usize, usize, String)> = match format {
+            "json" => serde_json::from_str::<serde_json::Value>(&text)
+                .map_err(|e| (e.line(), e.column(), e.to_string()))
+                .and_then(|v| serde_json::to_string_pretty(&v).map_err(|e| (0, 0, e.to_string()))),
+            "yaml" => serde_yaml::from_str::<serde_yaml::Value>(&text)
+                .map_err(|e| {
+                    let (line, column) = e.location().map_or((0, 0), |l| (l.line(), l.column()));
+                    (line, column, e.to_string())
+                })
+                .and_then(|v| serde_yaml::to_string(&v).map_err(|e| (0, 0, e.to_string()))),
+            _ => toml::from_str::<toml::Table>(&text)
+                .map_err(|e| {
+                    let offset = e.span().map_or(0, |span| span.start);
+                    let before = &text[..text.floor_char_boundary(offset)];
+                    let line = before.matches('\n').count() + 1;
+                    let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+                    (line, column, e.message().to_string())
+                })
+                .and_then(|v| toml::to_string_pretty(&v).map_err(|e| (0, 0, e.to_string()))),
+        };
+
+        match parsed {
+            Ok(pretty) if pretty.len() > config::get().max_result_bytes => format!(
+                "{} is valid {} but {} bytes when pretty-printed, over the {} byte limit; use read_file_page instead",
+                path,
+                format,
+                pretty.len(),
+                config::get().max_result_bytes
+            ),
+            Ok(pretty) => json!({ "format": format, "valid": true, "content": pretty }).to_string(),
+            Err((line, column, message)) => {
+                format!("Error: invalid {} in {} at line {}, column {}: {}", format, path, line, column, message)
+            }
+        }
+    }
+
+    /// Render a `{{var}}` template into `destination`, refusing if any placeholder is unresolved
+    fn render_template(&self, template: &str, destination: &str, vars: &serde_json::Value) -> String {
+        let (template_path, dest_path) = match (self.resolve_path(template), self.resolve_write_path(destination)) {
+            (Ok(t), Ok(d)) => (t, d),
+            (Err(e), _) | (_, Err(e)) => return e,
+        };
+        let source = match fs::read_to_string(&template_path) {
+            Ok(source) => source,
+            Err(e) => return format!("Error reading template: {}", e),
+        };
+
+        let mut rendered = String::with_capacity(source.len());
+        let mut unresolved = Vec::new();
+        let mut rest = source.as_str();
+        while let Some(start) = rest.find("{{") {
+            let Some(len) = rest[start..].find("}}") else {
+                let line = source[..source.len() - rest.len() + start].matches('\n').count() + 1;
+                return format!("Error: {} has a '{{{{' on line {} that is never closed", template, line);
+            };
+            rendered.push_str(&rest[..start]);
+            let name = rest[start + 2..start + len].trim();
+            match &vars[name] {
+                serde_json::Value::String(value) => rendered.push_str(value),
+                serde_json::Value::Null => unresolved.push(name.to_string()),
+                other => rendered.push_str(&other.to_string()),
+            }
+            rest = &rest[start + len + 2..];
+        }
+        rendered.push_str(rest);
+
+        if !unresolved.is_empty() {
+            return format!("Error: unresolved template variables: {}", unresolved.join(", "));
+        }
+
+        let _guard = file_lock::lock(&dest_path);
+        if unchanged(&dest_path, &rendered) {
+            return format!("No change needed: {} already matches the rendered template", destination);
+        }
+        if let Err(e) = undo::record(&self.write_root, &dest_path) {
+            return format!("Error writing file: {}", e);
+        }
+        match fs::write(&dest_path, rendered) {
+            Ok(_) => format!("Successfully rendered {} to {}", template, destination),
+            Err(e) => format!("Error writing file: {}", e),
+        }
+    }
+
+    /// Whole-word replace `from` with `to` in every file matching `pattern`, optionally as a dry run
+    fn rename_in_tree(&self, from: &str, to: &str, pattern: &str, dry_run: bool) -> String {
+        let identifier = Regex::new(r"^[A-Za-z_][A-Za-z0-9_]*$").expect("valid identifier regex");
+        if !identifier.is_match(from) || !identifier.is_match(to) {
+            return "Error: 'from' and 'to' must be plain identifiers".to_string();
+        }
+        let word = Regex::new(&format!(r"\b{}\b", regex::escape(from))).expect("escaped identifier is a valid regex");
+
+        let full_pattern = match self.resolve_path(pattern) {
+            Ok(full_pattern) => full_pattern,
+            Err(e) => return e,
+        };
+        let paths = match glob::glob(&full_pattern.to_string_lossy()) {
+            Ok(paths) => paths,
+            Err(e) => return format!("Error: invalid glob '{}': {}", pattern, e),
+        };
+
+        let mut files = Vec::new();
+        let mut diff = String::new();
+        let mut total = 0;
+        for path in paths.filter_map(|p| p.ok()).filter(|p| p.is_file()) {
+            let relative = path.strip_prefix(&self.base).unwrap_or(&path).display().to_string();
+            // Non-UTF8 files can't contain a renamed identifier we could safely rewrite
+            let Ok(content) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let count = word.find_iter(&content).count();
+            if count == 0 {
+                continue;
+            }
+
+            let updated = word.replace_all(&content, NoExpand(to)).into_owned();
+            diff.push_str(
+                &similar::TextDiff::from_lines(&content, &updated)
+                    .unified_diff()
+                    .context_radius(1)
+                    .header(&relative, &relative)
+                    .to_string(),
+            );
+
+            let mut result = json!({ "path": relative, "replacements": count });
+            if !dry_run {
+                let written = self.resolve_write_path(&relative).and_then(|target| {
+                    let _guard = file_lock::lock(&target);
+                    undo::record(&self.write_root, &target).map_err(|e| e.to_string())?;
+                    fs::write(&target, &updated).map_err(|e| e.to_string())
+                });
+                if let Err(e) = written {
+                    result["error"] = json!(e);
+                }
+            }
+            total += count;
+            files.push(result);
+        }
+
+        let budget = config::get().max_result_bytes;
+        if diff.len() > budget {
+            let cut = diff.floor_char_boundary(budget);
+            diff.truncate(cut);
+            diff.push_str("\n... [diff truncated]");
+        }
+
+        json!({
+            "dry_run": dry_run,
+            "total_replacements": total,
+            "files": files,
+            "diff": diff,
+        })
+        .to_string()
+    }
+
+    /// Unified diff between two files, or an explicit note when they match
+    fn diff_files(&self, path_a: &str, path_b: &str, context: usize) -> String {
+        let read = |path: &str| {
+            self.resolve_path(path)
+                .and_then(|full_path| fs::read_to_string(full_path).map_err(|e| format!("Error reading {}: {}", path, e)))
+        };
+        let (a, b) = match (read(path_a), read(path_b)) {
+            (Ok(a), Ok(b)) => (a, b),
+            (Err(e), _) | (_, Err(e)) => return e,
+        };
+
+        if a == b {
+            return "Files are identical".to_string();
+        }
+        similar::TextDiff::from_lines(&a, &b)
+            .unified_diff()
+            .context_radius(context)
+            .header(path_a, path_b)
+            .to_string()
+    }
+
+    /// Patterns from `.hiveignore` in the base directory, plus hive's own bookkeeping directories
+    fn ignore_patterns(&self) -> Vec<glob::Pattern> {
+        let mut lines = vec![".git".to_string(), undo::UNDO_DIR.to_string(), scratch::SCRATCH_DIR.to_string()];
+        if let Ok(content) = fs::read_to_string(self.base.join(".hiveignore")) {
+            lines.extend(
+                content
+                    .lines()
+                    .map(|l| l.trim().trim_end_matches('/').to_string())
+                    .filter(|l| !l.is_empty() && !l.starts_with('#')),
+            );
+        }
+        lines.iter().filter_map(|l| glob::Pattern::new(l).ok()).collect()
+    }
+
+    /// An entry is ignored if a pattern matches its name or its path relative to the base
+    fn is_ignored(&self, path: &Path, ignore: &[glob::Pattern]) -> bool {
+        let relative = path.strip_prefix(&self.base).unwrap_or(path);
+        let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+        ignore.iter().any(|p| p.matches(&name) || p.matches_path(relative))
+    }
+
+    /// Append one directory level of the tree, recursing until the depth or entry budget runs out
+    fn render_tree(&self, walk: &mut TreeWalk, dir: &Path, prefix: &str, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+
+        let mut entries: Vec<PathBuf> = entries
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| !self.is_ignored(p, walk.ignore))
+            .collect();
+        // Directories first, then files, each alphabetical; symlinks aren't followed, so they sort as files
+        let is_dir = |p: &Path| p.symlink_metadata().is_ok_and(|m| m.is_dir());
+        entries.sort_by_key(|p| (!is_dir(p), p.file_name().map(|n| n.to_os_string())));
+
+        let count = entries.len();
+        for (index, entry) in entries.into_iter().enumerate() {
+            if walk.remaining == 0 {
+                walk.truncated = true;
+                return;
+            }
+            walk.remaining -= 1;
+
+            let last = index + 1 == count;
+            let name = entry.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+            let marker = match fs::read_link(&entry) {
+                Ok(target) => format!(" -> {}", target.display()),
+                Err(_) if is_dir(&entry) => "/".to_string(),
+                Err(_) => String::new(),
+            };
+            walk.lines.push(format!("{}{}{}{}", prefix, if last { "└── " } else { "├── " }, name, marker));
+
+            if is_dir(&entry) {
+                let child_prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+                self.render_tree(walk, &entry, &child_prefix, depth - 1);
+            }
+        }
+    }
+
+    /// Apply an octal mode string (e.g. "755") to a file under the write root
+    #[cfg(unix)]
+    fn set_permissions(&self, path: &str, mode: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = match u32::from_str_radix(mode.trim().trim_start_matches("0o"), 8) {
+            Ok(mode) if mode <= 0o7777 => mode,
+            _ => return format!("Error: invalid mode '{}', expected octal such as 755", mode),
+        };
+        let full_path = match self.resolve_write_path(path) {
+            Ok(full_path) => full_path,
+            Err(e) => return e,
+        };
+        let _guard = file_lock::lock(&full_path);
+        match fs::set_permissions(&full_path, fs::Permissions::from_mode(mode)) {
+            Ok(_) => format!("Set permissions of {} to {:o}", path, mode),
+            Err(e) => format!("Error setting permissions: {}", e),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn set_permissions(&self, _path: &str, _mode: &str) -> String {
+        "Error: set_permissions is only supported on Unix".to_string()
+    }
+
+    /// Collect lines appended to `path` over `duration`, optionally keeping only regex matches
+    /// Stops early at the delegation's deadline or a shutdown, and polls off the async runtime's worker threads
+    fn tail_follow(&self, path: &str, duration: Duration, pattern: Option<&str>) -> String {
+        let full_path = match self.resolve_path(path) {
+            Ok(full_path) => full_path,
+            Err(e) => return e,
+        };
+        let filter = match pattern.map(Regex::new).transpose() {
+            Ok(filter) => filter,
+            Err(e) => return format!("Error: invalid regex: {}", e),
+        };
+        let mut file = match fs::File::open(&full_path) {
+            Ok(file) => file,
+            Err(e) => return format!("Error opening file: {}", e),
+        };
+        let mut position = match file.seek(SeekFrom::End(0)) {
+            Ok(position) => position,
+            Err(e) => return format!("Error reading file: {}", e),
+        };
+
+        let budget = config::get().max_result_bytes;
+        let deadline = budget::deadline().map_or(Instant::now() + duration, |cancel| cancel.min(Instant::now() + duration));
+        let mut pending = String::new();
+        let mut lines: Vec<String> = Vec::new();
+        let mut collected = 0;
+        let mut truncated = false;
+        blocking::block_in_place(|| {
+            while Instant::now() < deadline && !truncated && !shutdown::requested() {
+                // Start over if the file was truncated or rotated under us
+                if fs::metadata(&full_path).is_ok_and(|meta| meta.len() < position) {
+                    position = file.seek(SeekFrom::Start(0)).unwrap_or(0);
+                }
+                let mut chunk = Vec::new();
+                if let Ok(read) = file.read_to_end(&mut chunk) {
+                    position += read as u64;
+                    pending.push_str(&String::from_utf8_lossy(&chunk));
+                }
+                // Only complete lines; a partial last line waits for the rest
+                while let Some(end) = pending.find('\n') {
+                    let line: String = pending.drain(..=end).collect();
+                    let line = line.trim_end_matches(['\n', '\r']).to_string();
+                    if filter.as_ref().is_some_and(|re| !re.is_match(&line)) {
+                        continue;
+                    }
+                    if collected + line.len() > budget {
+                        truncated = true;
+                        break;
+                    }
+                    collected += line.len() + 1;
+                    lines.push(line);
+                }
+                std::thread::sleep(TAIL_POLL_INTERVAL);
+            }
+        });
+
+        json!({
+            "lines": lines,
+            "truncated": truncated,
+        })
+        .to_string()
+    }
+
+    /// Rows `start_row..start_row + max_rows` of a CSV file as header-keyed objects, plus the total row count
+    fn read_csv(&self, path: &str, columns: Option<&[&str]>, start_row: usize, max_rows: usize) -> String {
+        let full_path = match self.resolve_path(path) {
+            Ok(full_path) => full_path,
+            Err(e) => return e,
+        };
+        let is_tsv = full_path.extension().is_some_and(|e| e.eq_ignore_ascii_case("tsv"));
+        let mut reader = match csv::ReaderBuilder::new()
+            .delimiter(if is_tsv { b'\t' } else { b',' })
+            .flexible(true)
+            .from_path(&full_path)
+        {
+            Ok(reader) => reader,
+            Err(e) => return format!("Error opening CSV: {}", e),
+        };
+        let headers = match reader.headers() {
+            Ok(headers) => headers.clone(),
+            Err(e) => return format!("Error reading CSV header: {}", e),
+        };
+
+        // Indices of the requested columns, in the order asked for
+        let selected: Vec<(usize, &str)> = match columns {
+            Some(columns) if !columns.is_empty() => {
+                let mut selected = Vec::new();
+                for &column in columns {
+                    match headers.iter().position(|h| h == column) {
+                        Some(index) => selected.push((index, column)),
+                        None => {
+                            return format!(
+                                "Error: no column '{}' in {}; columns are: {}",
+                                column,
+                                path,
+                                headers.iter().collect::<Vec<_>>().join(", ")
+                            );
+                        }
+                    }
+                }
+                selected
+            }
+            _ => headers.iter().enumerate().collect(),
+        };
+
+        let mut rows = Vec::new();
+        let mut total_rows = 0;
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => return format!("Error parsing CSV: {}", e),
+            };
+            if total_rows >= start_row && rows.len() < max_rows {
+                let row: serde_json::Map<String, serde_json::Value> = selected
+                    .iter()
+                    .map(|&(index, name)| (name.to_string(), json!(record.get(index).unwrap_or(""))))
+                    .collect();
+                rows.push(row);
+            }
+            total_rows += 1;
+        }
+
+        let result = json!({
+            "columns": selected.iter().map(|&(_, name)| name).collect::<Vec<_>>(),
+            "total_rows": total_rows,
+            "start_row": start_row,
+            "rows": rows,
+        })
+        .to_string();
+        let budget = config::get().max_result_bytes;
+        if result.len() > budget {
+            return format!(
+                "{} rows of {} are {} bytes, over the {} byte limit; ask for fewer rows or columns",
+                rows.len(),
+                path,
+                result.len(),
+                budget
+            );
+        }
+        result
+    }
+
+    /// A symlink's target as written and, if it resolves, where it lands relative to the base
+    /// Only the link itself must be inside the base; reporting its target never touches it
+    fn read_link(&self, path: &str) -> String {
+        let link = match self.join_base(path) {
+            Ok(link) => link,
+            Err(e) => return e,
+        };
+        let parent = link.parent().unwrap_or(&link);
+        if !link.starts_with(&self.base) || self.escapes_via_symlink(parent) {
+            return format!("Error: path '{}' is outside the working directory", path);
+        }
+        let target = match fs::read_link(&link) {
+            Ok(target) => target,
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => return format!("Error: {} is not a symlink", path),
+            Err(e) => return format!("Error reading link: {}", e),
+        };
+        let resolved = fs::canonicalize(&link).ok();
+        json!({
+            "target": target.display().to_string(),
+            "exists": resolved.is_some(),
+            "inside_working_directory": resolved.as_ref().is_some_and(|r| r.starts_with(&self.base)),
+            "resolves_to": resolved
+                .as_ref()
+                .and_then(|r| r.strip_prefix(&self.base).ok())
+                .map(|r| r.display().to_string()),
+        })
+        .to_string()
+    }
+
+    /// Re-encode a file from `from` (or its BOM/detected encoding) to `to`, refusing lossy conversions
+    fn convert_encoding(&self, path: &str, from: Option<&str>, to: &str) -> String {
+        let full_path = match self.resolve_write_path(path) {
+            Ok(full_path) => full_path,
+            Err(e) => return e,
+        };
+        let Some(target) = Encoding::for_label(to.as_bytes()) else {
+            return format!("Error: unknown encoding '{}'", to);
+        };
+        let _guard = file_lock::lock(&full_path);
+        let bytes = match fs::read(&full_path) {
+            Ok(bytes) => bytes,
+            Err(e) => return format!("Error reading file: {}", e),
+        };
+        let source = match from {
+            Some(label) => match Encoding::for_label(label.as_bytes()) {
+                Some(encoding) => encoding,
+                None => return format!("Error: unknown encoding '{}'", label),
+            },
+            None => match Encoding::for_bom(&bytes) {
+                Some((encoding, _)) => encoding,
+                None if std::str::from_utf8(&bytes).is_ok() => UTF_8,
+                None => detect_encoding(&bytes),
+            },
+        };
+
+        let (text, had_errors) = source.decode_with_bom_removal(&bytes);
+        if had_errors {
+            return format!("Error: {} is not valid {}; pass the right encoding as 'from'", path, source.name());
+        }
+        let Some(converted) = encode_text(&text, target) else {
+            return format!("Error: {} has characters that {} can't represent; nothing was changed", path, target.name());
+        };
+        if converted == bytes {
+            return format!("No change needed: {} is already {}", path, target.name());
+        }
+        if let Err(e) = undo::record(&self.write_root, &full_path) {
+            return format!("Error converting file: {}", e);
+        }
+        match fs::write(&full_path, converted) {
+            Ok(()) => format!("Converted {} from {} to {}", path, source.name(), target.name()),
+            Err(e) => format!("Error writing file: {}", e),
+        }
+    }
+
+    /// Create a throwaway file under the write root's scratch directory, so it's inside the sandbox and writable
+    fn scratch_file(&self, extension: Option<&str>) -> String {
+        let extension = extension.map(|e| e.trim_start_matches('.')).filter(|e| !e.is_empty());
+        if let Some(extension) = extension
+            && !extension.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return format!("Error: invalid extension '{}' (letters and digits only)", extension);
+        }
+        match scratch::create(&self.write_root, extension) {
+            Ok(path) => path.strip_prefix(&self.base).unwrap_or(&path).display().to_string(),
+            Err(e) => format!("Error creating scratch file: {}", e),
+        }
+    }
+
+    /// Files under `path` newest first, optionally only those changed in the last `minutes`
+    /// Symlinked directories aren't followed, matching the tree view
+    fn recent_files(&self, path: &str, minutes: Option<u64>, limit: usize) -> String {
+        let root = match self.resolve_path(path) {
+            Ok(root) => root,
+            Err(e) => return e,
+        };
+        if !root.is_dir() {
+            return format!("Error: {} is not a directory", path);
+        }
+        // A window too long to take off the clock means no cutoff at all
+        let cutoff = minutes.and_then(|m| SystemTime::now().checked_sub(Duration::from_secs(m.saturating_mul(60))));
+
+        let ignore = self.ignore_patterns();
+        let mut files = Vec::new();
+        let mut pending = vec![root];
+        while let Some(dir) = pending.pop() {
+            let Ok(entries) = fs::read_dir(&dir) else {
+                continue;
+            };
+            for entry in entries.filter_map(|e| e.ok()) {
+                let entry_path = entry.path();
+                if self.is_ignored(&entry_path, &ignore) {
+                    continue;
+                }
+                let Ok(metadata) = entry_path.symlink_metadata() else {
+                    continue;
+                };
+                if metadata.is_dir() {
+                    pending.push(entry_path);
+                    continue;
+                }
+                let Ok(modified) = metadata.modified() else {
+                    continue;
+                };
+                if cutoff.is_none_or(|cutoff| modified >= cutoff) {
+                    files.push((modified, metadata.len(), entry_path));
+                }
+            }
+        }
+
+        files.sort_by_key(|file| std::cmp::Reverse(file.0));
+        let total = files.len();
+        let listed: Vec<_> = files
+            .into_iter()
+            .take(limit)
+            .map(|(modified, size, file)| {
+                json!({
+                    "path": file.strip_prefix(&self.base).unwrap_or(&file).display().to_string(),
+                    "modified": modified.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+                    "size": size,
+                })
+            })
+            .collect();
+        let mut result = json!({ "files": listed });
+        if total > limit {
+            result["truncated"] = json!(format!("showing the newest {} of {} files", limit, total));
+        }
+        result.to_string()
+    }
+
+    /// Insert `content` before `line`, or after it when `after` is set
+    fn insert_at_line(&self, path: &str, line: usize, content: &str, after: bool) -> String {
+        // Index of the first inserted line; before line 1 and after line 0 both mean the top
+        let index = match (after, line) {
+            (false, 0) => return "Error: line is 1-indexed; use after=true with line 0 to insert at the top".to_string(),
+            (false, line) => line - 1,
+            (true, line) => line,
+        };
+        if content.is_empty() {
+            return "Error: content is empty".to_string();
+        }
+        match self.splice_lines(path, index, 0, content) {
+            Ok(1) => format!("Inserted 1 line into {} at line {}", path, index + 1),
+            Ok(count) => format!("Inserted {} lines into {} at lines {}-{}", count, path, index + 1, index + count),
+            Err(e) => e,
+        }
+    }
+
+    /// Replace lines `start..=end` (1-indexed) with `content`; empty content deletes them
+    fn replace_lines(&self, path: &str, start: usize, end: usize, content: &str) -> String {
+        if start == 0 || end < start {
+            return format!("Error: invalid line range {}-{}", start, end);
+        }
+        match self.splice_lines(path, start - 1, end - start + 1, content) {
+            Ok(0) => format!("Deleted lines {}-{} of {}", start, end, path),
+            Ok(1) => format!("Replaced lines {}-{} of {} with 1 line, now line {}", start, end, path, start),
+            Ok(count) => format!(
+                "Replaced lines {}-{} of {} with {} lines, now lines {}-{}",
+                start,
+                end,
+                path,
+                count,
+                start,
+                start + count - 1
+            ),
+            Err(e) => e,
+        }
+    }
+
+    /// Swap `remove` lines at `index` (0-based) for the lines of `content`, returning how many were inserted
+    /// Multi-line content becomes one line per entry so later line numbers stay right
+    fn splice_lines(&self, path: &str, index: usize, remove: usize, content: &str) -> Result<usize, String> {
+        self.edit_lines(path, |lines| {
+            if index + remove > lines.len() {
+                return Err(format!("Error: {} has only {} lines", path, lines.len()));
+            }
+            let inserted: Vec<String> = content.lines().map(str::to_string).collect();
+            let count = inserted.len();
+            lines.splice(index..index + remove, inserted);
+            Ok(count)
+        })
+    }
+
+    /// Apply `edit` to a file's lines under its lock, writing the result back only if it changed
+    /// The file keeps its line ending (CRLF or LF) and whether it ends with one
+    fn edit_lines<T>(&self, path: &str, edit: impl FnOnce(&mut Vec<String>) -> Result<T, String>) -> Result<T, String> {
+        let full_path = self.resolve_write_path(path)?;
+        let _guard = file_lock::lock(&full_path);
+        let existing = fs::read_to_string(&full_path).map_err(|e| format!("Error reading file: {}", e))?;
+        let mut lines: Vec<String> = existing.lines().map(str::to_string).collect();
+        let output = edit(&mut lines)?;
+
+        let newline = if existing.contains("\r\n") { "\r\n" } else { "\n" };
+        let mut updated = lines.join(newline);
+        if !lines.is_empty() && (existing.is_empty() || existing.ends_with('\n')) {
+            updated.push_str(newline);
+        }
+        if updated != existing {
+            undo::record(&self.write_root, &full_path).map_err(|e| format!("Error writing file: {}", e))?;
+            fs::write(&full_path, updated).map_err(|e| format!("Error writing file: {}", e))?;
+        }
+        Ok(output)
+    }
+
+    /// Re-indent lines `start..=end` (1-indexed) to `width` spaces or one tab per level
+    /// Levels come from the file's indent step (its most common change in indent between lines, tabs counting as
+    /// `width` columns), so a nested region keeps its depth; leftover columns that don't make a full level are kept
+    /// as spaces, and nothing but leading whitespace changes
+    fn reindent(&self, path: &str, start: usize, end: Option<usize>, tabs: bool, width: usize) -> String {
+        if start == 0 || end.is_some_and(|end| end < start) {
+            return format!("Error: invalid line range {}-{}", start, end.unwrap_or(start));
+        }
+        let edited = self.edit_lines(path, |lines| {
+            let end = end.unwrap_or(lines.len());
+            if start > lines.len() || end > lines.len() {
+                return Err(format!("Error: {} has only {} lines", path, lines.len()));
+            }
+            let columns = |line: &str| {
+                line.chars()
+                    .take_while(|c| matches!(c, ' ' | '\t'))
+                    .map(|c| if c == '\t' { width } else { 1 })
+                    .sum::<usize>()
+            };
+            let indents: Vec<usize> = lines.iter().filter(|line| !line.trim().is_empty()).map(|line| columns(line)).collect();
+            // Most common step wins (the smaller on a tie), so the odd aligned continuation line doesn't set it
+            let mut steps = BTreeMap::new();
+            for pair in indents.windows(2).filter(|pair| pair[0] != pair[1]) {
+                *steps.entry(pair[0].abs_diff(pair[1])).or_insert(0) += 1;
+            }
+            let unit = steps
+                .into_iter()
+                .rev()
+                .max_by_key(|&(_, count)| count)
+                .map(|(step, _)| step)
+                .or_else(|| indents.iter().copied().filter(|&cols| cols > 0).min())
+                .unwrap_or(width);
+
+            let region = &mut lines[start - 1..end];
+
+            let mut changed = 0;
+            for line in region.iter_mut().filter(|line| !line.trim().is_empty()) {
+                let cols = columns(line);
+                let (levels, extra) = (cols / unit, cols % unit);
+                let indent = if tabs { "\t".repeat(levels) } else { " ".repeat(levels * width) } + &" ".repeat(extra);
+                let reindented = format!("{}{}", indent, line.trim_start_matches([' ', '\t']));
+                if *line != reindented {
+                    *line = reindented;
+                    changed += 1;
+                }
+            }
+            Ok((changed, end))
+        });
+        let style = if tabs { "tabs".to_string() } else { format!("{} spaces", width) };
+        match edited {
+            Ok((0, end)) => format!("No change needed: lines {}-{} of {} are already indented with {}", start, end, path, style),
+            Ok((changed, end)) => format!("Reindented {} of lines {}-{} in {} to {}", changed, start, end, path, style),
+            Err(e) => e,
+        }
+    }
+}
+
+/// Turn file bytes into something the model can use: the text, a lossy decode, or a binary preview
+/// Text in another encoding (given, marked by a BOM, or detected) is converted to UTF-8 with a note saying from what
+/// With `numbered`, each line of text (not the note) is prefixed with its line number
+fn decode_text(path: &str, bytes: Vec<u8>, encoding: Option<&'static Encoding>, numbered: bool) -> String {
+    const PREVIEW_BYTES: usize = 64;
+    let body = |text: &str| if numbered { number_lines(text, 1) } else { text.to_string() };
+
+    // UTF-16 is full of NUL bytes, so a known encoding has to be handled before the binary check
+    if let Some(encoding) = encoding.or_else(|| Encoding::for_bom(&bytes).map(|(encoding, _)| encoding)) {
+        let (text, had_errors) = encoding.decode_with_bom_removal(&bytes);
+        return match (encoding == UTF_8, had_errors) {
+            (true, false) => body(&text),
+            (false, false) => format!("[Decoded from {}]\n{}", encoding.name(), body(&text)),
+            (_, true) => format!(
+                "[Warning: {} is not valid {}; invalid bytes were replaced with U+FFFD]\n{}",
+                path,
+                encoding.name(),
+                body(&text)
+            ),
+        };
+    }
+
+    // NUL bytes almost never appear in text, so treat them as a binary marker
+    if bytes.iter().take(8192).any(|&b| b == 0) {
+        let preview: Vec<String> = bytes.iter().take(PREVIEW_BYTES).map(|b| format!("{:02x}", b)).collect();
+        return format!(
+            "{} appears to be binary ({} bytes); first {} bytes as hex:\n{}",
+            path,
+            bytes.len(),
+            preview.len(),
+            preview.join(" ")
+        );
+    }
+
+    let bytes = match String::from_utf8(bytes) {
+        Ok(text) => return body(&text),
+        Err(e) => e.into_bytes(),
+    };
+    // Legacy single- and multi-byte encodings decode without errors when the guess is right
+    let guess = detect_encoding(&bytes);
+    if guess != UTF_8
+        && let (text, false) = guess.decode_without_bom_handling(&bytes)
+    {
+        return format!("[Decoded from {} (detected)]\n{}", guess.name(), body(&text));
+    }
+
+    match String::from_utf8(bytes) {
+        Ok(text) => body(&text),
+        Err(e) if config::get().lossy_utf8 => format!(
+            "[Warning: {} is not valid UTF-8; invalid bytes were replaced with U+FFFD]\n{}",
+            path,
+            body(&String::from_utf8_lossy(e.as_bytes()))
+        ),
+        Err(e) => format!("Error reading file: {} is not valid UTF-8 ({})", path, e.utf8_error()),
+    }
+}
+
+/// Prefix each line of `text` with its number, counting from `first` and padded so the content lines up
+fn number_lines(text: &str, first: usize) -> String {
+    let width = (first + text.lines().count().saturating_sub(1)).to_string().len();
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| format!("{:>width$}: {}", first + i, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Best guess at the encoding of non-UTF-8 text
+fn detect_encoding(bytes: &[u8]) -> &'static Encoding {
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// `text` in `encoding`, or `None` if it has characters the encoding can't represent
+/// UTF-16 gets a BOM so it can be recognised when read back
+fn encode_text(text: &str, encoding: &'static Encoding) -> Option<Vec<u8>> {
+    if encoding == UTF_16LE || encoding == UTF_16BE {
+        let little_endian = encoding == UTF_16LE;
+        let bytes = std::iter::once(0xFEFF)
+            .chain(text.encode_utf16())
+            .flat_map(|unit| if little_endian { unit.to_le_bytes() } else { unit.to_be_bytes() })
+            .collect();
+        return Some(bytes);
+    }
+    let (bytes, _, unmappable) = encoding.encode(text);
+    (!unmappable).then(|| bytes.into_owned())
+}
+
+/// Decompress a `.gz`/`.bz2` file, keeping at most `budget` bytes of output
+/// Returns `None` for other extensions; the flag says whether output was cut off
+fn read_decompressed(path: &Path, budget: usize) -> Option<io::Result<(Vec<u8>, bool)>> {
+    let extension = path.extension().and_then(|e| e.to_str());
+    if !matches!(extension, Some("gz" | "bz2")) {
+        return None;
+    }
+    let file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) => return Some(Err(e)),
+    };
+    let reader: Box<dyn Read> = if extension == Some("gz") {
+        Box::new(flate2::read::MultiGzDecoder::new(file))
+    } else {
+        Box::new(bzip2::read::MultiBzDecoder::new(file))
+    };
+
+    // Read one byte past the budget to tell "exactly full" from "cut off"
+    let mut bytes = Vec::new();
+    if let Err(e) = reader.take(budget as u64 + 1).read_to_end(&mut bytes) {
+        return Some(Err(e));
+    }
+    let truncated = bytes.len() > budget;
+    if truncated {
+        bytes.truncate(budget);
+        // Don't leave half a UTF-8 character at the cut
+        if let Err(e) = std::str::from_utf8(&bytes)
+            && e.error_len().is_none()
+        {
+            bytes.truncate(e.valid_up_to());
+        }
+    }
+    Some(Ok((bytes, truncated)))
+}
+
+/// State threaded through `render_tree`
+struct TreeWalk<'a> {
+    ignore: &'a [glob::Pattern],
+    remaining: usize,
+    truncated: bool,
+    lines: Vec<String>,
+}
+
+/// Whether `path` already holds exactly `content`, so writing it would be a no-op
+fn unchanged(path: &Path, content: &str) -> bool {
+    fs::read(path).is_ok_and(|existing| existing == content.as_bytes())
+}
+
+/// Tool results report failures as text; this recognizes them
+fn is_error(result: &str) -> bool {
+    result.starts_with("Error") || result.starts_with("Unknown tool")
+}
+
+/// Longest a single `tail_follow` call may watch a file
+const MAX_TAIL_SECS: u64 = 60;
+const TAIL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Rows `read_csv` returns when the model doesn't say how many
+const DEFAULT_CSV_ROWS: u64 = 100;
+
+/// Files `recent_files` returns when the model doesn't say how many
+const DEFAULT_RECENT_FILES: u64 = 20;
+
+const SYSTEM_PROMPT: &str = r#"You are FileManager, a specialized Worker in the Hive system focused on file operations.
+
+IMPORTANT: Always respond in English.
+
+# Your Role
+You receive file-related tasks from the Queen and execute them using your available tools. You MUST use your tools to complete tasks - do not claim you cannot access files.
+
+# How to Work
+1. When asked to read a file, USE the read_file tool (or read_files for several at once)
+2. When asked to write a file, USE the write_file tool
+3. When asked to list files, USE the list_directory tool (or directory_tree for an overview)
+4. After changing code, USE the check_code tool and report any diagnostics
+5. For intermediate files you don't want to keep, USE scratch_file instead of writing into the project
+6. Always use tools first, then report results
+
+# Operational Guidelines
+- Use your tools to complete tasks
+- Handle errors gracefully and report them clearly
+- For complex tasks, break them into multiple tool calls
+- To find out what changed recently, use recent_files rather than listing directories
+- To add or change lines in an existing file, use insert_at_line or replace_lines rather than rewriting the whole file; read it with numbered=true first so the line numbers are exact, and fix inconsistent indentation with reindent
+- Be explicit about what succeeded vs. failed
+
+# Constraints
+- Stay focused on file operations
+- Always respond in English
+
+You are efficient, reliable, and always use your tools to complete tasks."#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A file manager rooted in a fresh temporary directory
+    fn scratch_manager() -> (FileManager, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("hive-test-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        (FileManager::new(dir.to_str()), dir)
+    }
+
+    #[test]
+    fn insert_at_line_places_a_block() {
+        let (manager, dir) = scratch_manager();
+        fs::write(dir.join("notes.txt"), "one\ntwo\nthree\n").unwrap();
+
+        let result = manager.insert_at_line("notes.txt", 2, "a\nb\nc", false);
+        assert_eq!(result, "Inserted 3 lines into notes.txt at lines 2-4");
+        let text = fs::read_to_string(dir.join("notes.txt")).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines, ["one", "a", "b", "c", "two", "three"]);
+        assert!(text.ends_with('\n'));
+
+        manager.insert_at_line("notes.txt", 6, "x\ny\nz", true);
+        let text = fs::read_to_string(dir.join("notes.txt")).unwrap();
+        assert_eq!(text.lines().count(), 9);
+        assert_eq!(text.lines().skip(6).collect::<Vec<_>>(), ["x", "y", "z"]);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn render_template_fills_placeholders() {
+        let (manager, dir) = scratch_manager();
+        fs::write(dir.join("greeting.tpl"), "Hello {{ name }}, you have {{count}} messages\n").unwrap();
+
+        let vars = json!({"name": "Ada", "count": 3});
+        assert_eq!(manager.render_template("greeting.tpl", "greeting.txt", &vars), "Successfully rendered greeting.tpl to greeting.txt");
+        assert_eq!(fs::read_to_string(dir.join("greeting.txt")).unwrap(), "Hello Ada, you have 3 messages\n");
+
+        let result = manager.render_template("greeting.tpl", "partial.txt", &json!({"name": "Ada"}));
+        assert_eq!(result, "Error: unresolved template variables: count");
+        assert!(!dir.join("partial.txt").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn render_template_refuses_an_unclosed_placeholder() {
+        let (manager, dir) = scratch_manager();
+        fs::write(dir.join("broken.tpl"), "a {{ b }}\nc {{ d").unwrap();
+
+        let result = manager.render_template("broken.tpl", "out.txt", &json!({"b": "x", "d": "y"}));
+        assert_eq!(result, "Error: broken.tpl has a '{{' on line 2 that is never closed");
+        assert!(!dir.join("out.txt").exists());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn tail_follow_runs_on_a_current_thread_runtime() {
+        let (manager, dir) = scratch_manager();
+        let log = dir.join("app.log");
+        fs::write(&log, "old\n").unwrap();
+        let writer = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(100));
+            let mut file = fs::OpenOptions::new().append(true).open(log).unwrap();
+            std::io::Write::write_all(&mut file, b"ready\nnoise\n").unwrap();
+        });
+
+        let result = manager.tail_follow("app.log", Duration::from_millis(400), Some("^re"));
+        writer.join().unwrap();
+        assert_eq!(result, r#"{"lines":["ready"],"truncated":false}"#);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn recent_files_takes_any_window() {
+        let (manager, dir) = scratch_manager();
+        fs::write(dir.join("fresh.txt"), "new").unwrap();
+
+        assert!(manager.recent_files(".", Some(5), 10).contains("fresh.txt"));
+        assert!(manager.recent_files(".", Some(u64::MAX), 10).contains("fresh.txt"));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn decode_text_handles_encodings_and_binary() {
+        assert_eq!(decode_text("a.txt", b"hi\nthere".to_vec(), None, true), "1: hi\n2: there");
+        assert_eq!(decode_text("a.txt", vec![0xFF, 0xFE, b'h', 0, b'i', 0], None, false), "[Decoded from UTF-16LE]\nhi");
+        assert_eq!(
+            decode_text("a.bin", vec![0, 1, 0xAB], None, false),
+            "a.bin appears to be binary (3 bytes); first 3 bytes as hex:\n00 01 ab"
+        );
+        let latin1 = encoding_rs::WINDOWS_1252.encode("Le café était déjà fermé à côté de l'école").0.into_owned();
+        assert_eq!(
+            decode_text("menu.txt", latin1, None, false),
+            "[Decoded from windows-1252 (detected)]\nLe café était déjà fermé à côté de l'école"
+        );
+        let given = decode_text("a.txt", vec![b'o', b'k', 0xFF], Some(UTF_16BE), false);
+        assert!(given.starts_with("[Warning: a.txt is not valid UTF-16BE"));
+    }
+
+    #[test]
+    fn read_lines_checks_the_range_before_reading() {
+        let (manager, dir) = scratch_manager();
+        fs::write(dir.join("notes.txt"), "one\ntwo\nthree\n").unwrap();
+        let read = |args: serde_json::Value| manager.execute_tool("read_lines", &args).unwrap();
+
+        assert_eq!(read(json!({"path": "notes.txt", "start_line": 0})), "Error: invalid line range 0-99");
+        assert_eq!(read(json!({"path": "missing.txt", "start_line": 3, "end_line": 2})), "Error: invalid line range 3-2");
+        assert_eq!(read(json!({"path": "notes.txt", "start_line": 4})), "Error: notes.txt has only 3 lines");
+        assert_eq!(read(json!({"path": "notes.txt", "start_line": 2, "end_line": 9})), "two\nthree");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn reindent_keeps_a_nested_region_at_its_depth() {
+        let (manager, dir) = scratch_manager();
+        let source = "fn main() {\n    if ready {\n        start();\n            .await;\n        stop();\n    }\n}\n";
+        fs::write(dir.join("main.rs"), source).unwrap();
+
+        assert!(manager.reindent("main.rs", 3, Some(5), false, 4).starts_with("No change needed"));
+        manager.reindent("main.rs", 3, Some(5), true, 4);
+        let text = fs::read_to_string(dir.join("main.rs")).unwrap();
+        assert_eq!(text.lines().skip(2).take(3).collect::<Vec<_>>(), ["\t\tstart();", "\t\t\t.await;", "\t\tstop();"]);
+        assert_eq!(text.lines().next(), Some("fn main() {"));
+
+        assert_eq!(manager.reindent("main.rs", 9, None, true, 4), "Error: main.rs has only 7 lines");
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
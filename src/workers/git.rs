@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use crate::config;
+use crate::workspace::WorkspaceRoot;
+use crate::traits::{Capabilities, Worker, WorkerFactory, Agent, RunOptions, Tool, ToolFunction};
+use super::normalize;
+
+inventory::submit! {
+    WorkerFactory(|| Box::new(Git::new(None)))
+}
+
+pub struct Git {
+    /// Repository directory; paths given to tools must stay inside it
+    base: PathBuf,
+}
+
+#[async_trait]
+impl Worker for Git {
+    fn role(&self) -> &'static str {
+        "git"
+    }
+
+    fn description(&self) -> &'static str {
+        "Answers questions about the repository's history, such as who last changed a range of lines and when"
+    }
+
+    async fn process(&self, instruction: &str, options: &RunOptions) -> Result<String> {
+        Agent::run(self, &self.preprocess_instruction(instruction), options)
+            .await
+            .map(|result| self.postprocess_output(result))
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        Agent::get_tools(self)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { reads: true, shell: true, ..Capabilities::default() }
+    }
+
+    fn model_name(&self) -> Option<&'static str> {
+        Some(Agent::model(self))
+    }
+}
+
+impl Agent for Git {
+    fn ollama_url(&self) -> &'static str {
+        "http://localhost:11434/api/chat"
+    }
+
+    fn model(&self) -> &'static str {
+        "qwen2.5:14b"
+    }
+
+    fn system_prompt(&self) -> &'static str {
+        SYSTEM_PROMPT
+    }
+
+    fn client(&self) -> Client {
+        Client::new()
+    }
+
+    fn config_name(&self) -> &'static str {
+        Worker::role(self)
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        let tools = vec![
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "git_blame".to_string(),
+                    description: "Show who last changed each line in a range, as JSON [{line_number, commit, author, date, line}]".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "path": {
+                                "type": "string",
+                                "description": "Path to the file"
+                            },
+                            "start_line": {
+                                "type": "integer",
+                                "description": "First line to blame (1-based)"
+                            },
+                            "end_line": {
+                                "type": "integer",
+                                "description": "Last line to blame (inclusive)"
+                            }
+                        },
+                        "required": ["path", "start_line", "end_line"]
+                    }),
+                },
+            },
+        ];
+
+        // Only expose the tools this deployment enables for git
+        tools
+            .into_iter()
+            .filter(|tool| config::get().tool_enabled(self.role(), &tool.function.name))
+            .collect()
+    }
+
+    fn execute_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
+        if !config::get().tool_enabled(self.role(), name) {
+            return Ok(format!("Error: tool '{}' is disabled for {}", name, self.role()));
+        }
+
+        match name {
+            "git_blame" => {
+                let path = args["path"].as_str().unwrap_or("");
+                let start = args["start_line"].as_u64().unwrap_or(1).max(1);
+                let end = args["end_line"].as_u64().unwrap_or(start);
+                if end < start {
+                    return Ok(format!("Error: end_line {} is before start_line {}", end, start));
+                }
+                Ok(self.git_blame(path, start, end))
+            }
+            _ => Ok(format!("Unknown tool: {}", name)),
+        }
+    }
+}
+
+impl Git {
+    pub fn new(path: Option<&str>) -> Self {
+        let base = match path {
+            Some(p) => fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p)),
+            None => WorkspaceRoot::shared().worker_base("git"),
+        };
+        Git { base }
+    }
+
+    /// Resolve a model-supplied path against the base, refusing anything that escapes it
+    fn resolve_path(&self, path: &str) -> Result<PathBuf, String> {
+        let resolved = normalize(&self.base.join(path));
+        if resolved.starts_with(&self.base) {
+            Ok(resolved)
+        } else {
+            Err(format!("Error: path '{}' is outside the repository", path))
+        }
+    }
+
+    fn git_blame(&self, path: &str, start: u64, end: u64) -> String {
+        let full_path = match self.resolve_path(path) {
+            Ok(full_path) => full_path,
+            Err(e) => return e,
+        };
+        let output = Command::new("git")
+            .args(["blame", "--porcelain", "-L", &format!("{},{}", start, end), "--"])
+            .arg(&full_path)
+            .current_dir(&self.base)
+            .output();
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => return format!("Error running git: {}", e),
+        };
+        if !output.status.success() {
+            return format!("Error: git blame failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        let blame = parse_porcelain(&String::from_utf8_lossy(&output.stdout));
+        let result = json!(blame).to_string();
+        let budget = config::get().max_result_bytes;
+        if result.len() > budget {
+            return format!(
+                "Blame for {} lines {}-{} is {} bytes, over the {} byte limit; ask for a smaller range",
+                path,
+                start,
+                end,
+                result.len(),
+                budget
+            );
+        }
+        result
+    }
+}
+
+/// Turn `git blame --porcelain` output into one `{line_number, commit, author, date, line}` per line
+/// Commit details are only printed the first time a commit appears, so they're remembered by hash
+fn parse_porcelain(output: &str) -> Vec<serde_json::Value> {
+    let mut commits: HashMap<&str, (&str, i64, &str)> = HashMap::new();
+    let mut lines = Vec::new();
+    let mut current: Option<(&str, u64)> = None;
+    let (mut author, mut time, mut tz) = ("", 0, "+0000");
+
+    for row in output.lines() {
+        if let Some(text) = row.strip_prefix('\t') {
+            let Some((commit, line_number)) = current.take() else {
+                continue;
+            };
+            let (author, time, tz) = *commits.entry(commit).or_insert((author, time, tz));
+            lines.push(json!({
+                "line_number": line_number,
+                "commit": &commit[..commit.len().min(12)],
+                "author": author,
+                "date": format_date(time, tz),
+                "line": text,
+            }));
+        } else if let Some(value) = row.strip_prefix("author ") {
+            author = value;
+        } else if let Some(value) = row.strip_prefix("author-time ") {
+            time = value.parse().unwrap_or(0);
+        } else if let Some(value) = row.strip_prefix("author-tz ") {
+            tz = value;
+        } else {
+            // Header: <commit> <original line> <final line> [<group size>]
+            let mut fields = row.split(' ');
+            if let (Some(commit), Some(_), Some(final_line)) = (fields.next(), fields.next(), fields.next())
+                && commit.len() == 40
+                && commit.bytes().all(|b| b.is_ascii_hexdigit())
+            {
+                current = final_line.parse().ok().map(|n| (commit, n));
+            }
+        }
+    }
+    lines
+}
+
+/// Format a Unix timestamp in the author's `+HHMM` zone as `YYYY-MM-DD`
+fn format_date(secs: i64, tz: &str) -> String {
+    let sign = if tz.starts_with('-') { -1 } else { 1 };
+    let digits = tz.trim_start_matches(['+', '-']);
+    let hours: i64 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+    let minutes: i64 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+    let days = (secs + sign * (hours * 3600 + minutes * 60)).div_euclid(86_400);
+
+    // Civil-from-days (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+const SYSTEM_PROMPT: &str = r#"You are Git, a specialized Worker in the Hive system that answers questions about repository history.
+
+IMPORTANT: Always respond in English.
+
+# Your Role
+You receive history questions from the Queen ("who changed this", "when was this added") and answer them with your tools. You MUST use your tools - do not guess authors, dates or commits.
+
+# How to Work
+1. Use git_blame on the lines in question
+2. Group consecutive lines that share a commit when reporting
+3. Report commit hashes exactly as returned
+
+# Constraints
+- Stay focused on repository history
+- Always respond in English
+
+You are precise, factual, and always check the history before answering."#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_porcelain_remembers_commits_by_hash() {
+        let commit = "0123456789abcdef0123456789abcdef01234567";
+        let output = format!(
+            "{commit} 1 1 2\nauthor Ada\nauthor-mail <ada@example.com>\nauthor-time 86400\nauthor-tz +0000\n\
+             summary first\nfilename a.rs\n\tfn main() {{\n{commit} 2 2\n\t}}\n"
+        );
+        let lines = parse_porcelain(&output);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], json!({"line_number": 1, "commit": "0123456789ab", "author": "Ada", "date": "1970-01-02", "line": "fn main() {"}));
+        assert_eq!(lines[1]["author"], "Ada");
+        assert_eq!(lines[1]["line"], "}");
+    }
+
+    #[test]
+    fn format_date_uses_the_author_zone() {
+        assert_eq!(format_date(951_782_400, "+0000"), "2000-02-29");
+        assert_eq!(format_date(0, "-0100"), "1969-12-31");
+        assert_eq!(format_date(1_700_000_000, "+0530"), "2023-11-15");
+        assert_eq!(format_date(1_700_000_000, "garbage"), "2023-11-14");
+    }
+}
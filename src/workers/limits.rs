@@ -0,0 +1,239 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, OnceLock, mpsc};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::config;
+
+/// Output of a command run by `run_limited`
+pub(super) struct LimitedOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub status: ExitStatus,
+    output_exceeded: bool,
+    /// The timeout the command was killed at, if it ran that long
+    timed_out: Option<Duration>,
+}
+
+impl LimitedOutput {
+    /// Which configured limit got the command killed, if any
+    pub fn limit_exceeded(&self) -> Option<String> {
+        let config = config::get();
+        if self.output_exceeded {
+            return Some(format!("output limit of {} bytes", config.shell_max_output_bytes));
+        }
+        if let Some(timeout) = self.timed_out {
+            return Some(format!("time limit of {}s", timeout.as_secs()));
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            // The kernel sends SIGXCPU at the soft CPU limit and SIGKILL at the hard one
+            const SIGKILL: i32 = 9;
+            const SIGXCPU: i32 = 24;
+            if config.shell_max_cpu_secs > 0 && matches!(self.status.signal(), Some(SIGXCPU | SIGKILL)) {
+                return Some(format!("CPU time limit of {}s", config.shell_max_cpu_secs));
+            }
+            // Under `ulimit -v` allocations fail rather than the kernel killing anything, so the
+            // program aborts or crashes on its own
+            const SIGABRT: i32 = 6;
+            const SIGBUS: i32 = 7;
+            const SIGSEGV: i32 = 11;
+            if config.shell_max_memory_mb > 0 && matches!(self.status.signal(), Some(SIGABRT | SIGBUS | SIGSEGV)) {
+                return Some(format!("memory limit of {} MB", config.shell_max_memory_mb));
+            }
+        }
+        // ... or reports the failed allocation and exits
+        if config.shell_max_memory_mb > 0 && !self.status.success() {
+            let stderr = String::from_utf8_lossy(&self.stderr).to_ascii_lowercase();
+            if ALLOCATION_FAILURES.iter().any(|failure| stderr.contains(failure)) {
+                return Some(format!("memory limit of {} MB", config.shell_max_memory_mb));
+            }
+        }
+        None
+    }
+}
+
+/// How programs commonly report a failed allocation on stderr, lowercased
+const ALLOCATION_FAILURES: &[&str] = &[
+    "cannot allocate memory",
+    "out of memory",
+    "memoryerror",
+    "memory allocation failed",
+    "memory exhausted",
+    "bad_alloc",
+];
+
+/// Run `script` with `sh -c` in `dir` under the configured CPU, memory and output limits
+/// `args` become the script's `$0`, `$1`, ...; past `timeout` (if any) the command is killed
+pub(super) fn run_limited(dir: &Path, script: &str, args: &[&OsStr], timeout: Option<Duration>) -> io::Result<LimitedOutput> {
+    let config = config::get();
+    // ulimit applies to the subshell and everything it starts
+    let mut limited = String::new();
+    if config.shell_max_cpu_secs > 0 {
+        limited.push_str(&format!("ulimit -t {} || exit 126; ", config.shell_max_cpu_secs));
+    }
+    if config.shell_max_memory_mb > 0 {
+        limited.push_str(&format!("ulimit -v {} || exit 126; ", config.shell_max_memory_mb * 1024));
+    }
+    limited.push_str(script);
+
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(&limited)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    // A group of its own, so anything the command leaves running in the background can be traced back to hive
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    let mut child = command.spawn()?;
+    let pgid = child.id();
+    spawned_groups().lock().unwrap_or_else(|e| e.into_inner()).insert(pgid);
+
+    let max_output = config.shell_max_output_bytes;
+    let exceeded = Arc::new(AtomicBool::new(false));
+    let buffers = [Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new()))];
+    let (done, finished) = mpsc::channel();
+    let pipes = [
+        child.stdout.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+        child.stderr.take().map(|pipe| Box::new(pipe) as Box<dyn Read + Send>),
+    ];
+    for (pipe, buffer) in pipes.into_iter().zip(&buffers) {
+        let (exceeded, buffer, done) = (exceeded.clone(), buffer.clone(), done.clone());
+        thread::spawn(move || {
+            read_capped(pipe, max_output, &buffer, &exceeded);
+            let _ = done.send(());
+        });
+    }
+
+    // Poll rather than wait so a runaway writer can be killed mid-flight
+    // Start fast and back off, so short commands aren't rounded up to the polling interval
+    let started = Instant::now();
+    let mut timed_out = None;
+    let mut poll = Duration::from_millis(1);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if timeout.is_some_and(|limit| started.elapsed() >= limit) {
+            timed_out = timeout;
+            kill_group(&mut child, pgid);
+        }
+        if exceeded.load(Ordering::Relaxed) {
+            kill_group(&mut child, pgid);
+        }
+        thread::sleep(poll);
+        poll = (poll * 2).min(MAX_POLL);
+    };
+
+    // Something the command left in the background can hold the pipes open indefinitely, so
+    // once the leader is gone take what has arrived shortly after rather than waiting for EOF
+    let deadline = Instant::now() + READ_GRACE;
+    for _ in &buffers {
+        if finished.recv_timeout(deadline.saturating_duration_since(Instant::now())).is_err() {
+            break;
+        }
+    }
+    let [stdout, stderr] = buffers.map(|buffer| std::mem::take(&mut *buffer.lock().unwrap_or_else(|e| e.into_inner())));
+    // Most commands leave nothing running; forget their group now, before its id can be reused
+    if !group_alive(pgid) {
+        spawned_groups().lock().unwrap_or_else(|e| e.into_inner()).remove(&pgid);
+    }
+
+    Ok(LimitedOutput {
+        stdout,
+        stderr,
+        status,
+        output_exceeded: exceeded.load(Ordering::Relaxed),
+        timed_out,
+    })
+}
+
+/// Longest wait between checks on a running command
+const MAX_POLL: Duration = Duration::from_millis(20);
+
+/// How long after the command exits its output may still be read
+const READ_GRACE: Duration = Duration::from_millis(250);
+
+/// Kill the command's whole process group, so pipeline members and background children go with it
+fn kill_group(child: &mut Child, pgid: u32) {
+    #[cfg(unix)]
+    {
+        let _ = Command::new("kill").args(["-s", "KILL", "--", &format!("-{}", pgid)]).output();
+    }
+    #[cfg(not(unix))]
+    let _ = pgid;
+    let _ = child.kill();
+}
+
+/// Process groups started by `run_limited`, named by their leader's pid
+fn spawned_groups() -> &'static Mutex<HashSet<u32>> {
+    static GROUPS: OnceLock<Mutex<HashSet<u32>>> = OnceLock::new();
+    GROUPS.get_or_init(|| Mutex::new(HashSet::new()))
+}
+
+/// Whether process group `pgid` was started by hive (directly or by one of its commands)
+#[cfg(feature = "shell")]
+pub(super) fn spawned_by_hive(pgid: u32) -> bool {
+    spawned_groups().lock().unwrap_or_else(|e| e.into_inner()).contains(&pgid)
+}
+
+/// Forget hive's groups that aren't in `alive`, so an unrelated group that later gets the same id isn't taken for one
+/// Each is checked again first, since a command started after `alive` was taken won't be in it
+#[cfg(feature = "shell")]
+pub(super) fn forget_exited_groups(alive: &HashSet<u32>) {
+    spawned_groups().lock().unwrap_or_else(|e| e.into_inner()).retain(|&pgid| alive.contains(&pgid) || group_alive(pgid));
+}
+
+/// Whether any process other than an unreaped zombie is left in group `pgid`
+fn group_alive(pgid: u32) -> bool {
+    let Ok(output) = Command::new("ps").args(["-eo", "pgid=,stat="]).output() else {
+        return false;
+    };
+    String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        let mut fields = line.split_whitespace();
+        fields.next().and_then(|group| group.parse().ok()) == Some(pgid) && fields.next().is_some_and(|stat| !stat.starts_with('Z'))
+    })
+}
+
+/// Read `pipe` to the end into `kept`, keeping at most `max_bytes` (0 for no limit) and flagging `exceeded` past it
+/// Keeps draining after the cap so the writer isn't blocked before it's killed
+fn read_capped(pipe: Option<Box<dyn Read + Send>>, max_bytes: usize, kept: &Mutex<Vec<u8>>, exceeded: &AtomicBool) {
+    let Some(mut pipe) = pipe else {
+        return;
+    };
+    let mut chunk = [0u8; 8192];
+    while let Ok(read) = pipe.read(&mut chunk) {
+        if read == 0 {
+            break;
+        }
+        let mut kept = kept.lock().unwrap_or_else(|e| e.into_inner());
+        let room = if max_bytes == 0 { read } else { max_bytes.saturating_sub(kept.len()).min(read) };
+        kept.extend_from_slice(&chunk[..room]);
+        if room < read {
+            exceeded.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Cut `text` to at most `max_bytes` (on a char boundary), noting how much was dropped
+#[cfg(any(feature = "shell", feature = "python_runner"))]
+pub(super) fn truncate(mut text: String, max_bytes: usize) -> String {
+    if text.len() > max_bytes {
+        let dropped = text.len() - text.floor_char_boundary(max_bytes);
+        text.truncate(text.floor_char_boundary(max_bytes));
+        text.push_str(&format!("\n[truncated {} bytes]", dropped));
+    }
+    text
+}
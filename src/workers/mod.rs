@@ -1 +1,34 @@
-mod file_manager;
\ No newline at end of file
+#[cfg(feature = "file_manager")]
+mod file_manager;
+#[cfg(feature = "shell")]
+mod shell;
+#[cfg(feature = "web_search")]
+mod web_search;
+#[cfg(feature = "git")]
+mod git;
+#[cfg(feature = "python_runner")]
+mod python_runner;
+#[cfg(any(feature = "file_manager", feature = "shell", feature = "python_runner"))]
+mod limits;
+
+#[cfg(feature = "shell")]
+pub use shell::context_output;
+
+#[cfg(any(feature = "file_manager", feature = "shell", feature = "git"))]
+use std::path::{Component, Path, PathBuf};
+
+/// Lexically resolve `.` and `..` so paths can be compared without touching the filesystem
+#[cfg(any(feature = "file_manager", feature = "shell", feature = "git"))]
+fn normalize(path: &Path) -> PathBuf {
+    let mut resolved = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                resolved.pop();
+            }
+            Component::CurDir => {}
+            other => resolved.push(other),
+        }
+    }
+    resolved
+}
@@ -0,0 +1,204 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use uuid::Uuid;
+use crate::{blocking, config};
+use crate::workspace::WorkspaceRoot;
+use crate::traits::{Capabilities, Worker, WorkerFactory, Agent, RunOptions, Tool, ToolFunction};
+use super::limits::{run_limited, truncate};
+
+inventory::submit! {
+    WorkerFactory(|| Box::new(PythonRunner::new(None)))
+}
+
+/// Snippets are not sandboxed: they run with hive's own permissions and can reach any file it can,
+/// so the runner refuses to run at all where writes are meant to be confined
+pub struct PythonRunner {
+    /// Directory snippets run in
+    base: PathBuf,
+    /// Why snippets can't run here, when `worker_roots` or `write_root` confine this worker
+    confined: Option<String>,
+}
+
+#[async_trait]
+impl Worker for PythonRunner {
+    fn role(&self) -> &'static str {
+        "python_runner"
+    }
+
+    fn description(&self) -> &'static str {
+        "Runs Python snippets for calculations and data wrangling, and reports their output and exit status"
+    }
+
+    async fn process(&self, instruction: &str, options: &RunOptions) -> Result<String> {
+        Agent::run(self, &self.preprocess_instruction(instruction), options)
+            .await
+            .map(|result| self.postprocess_output(result))
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        Agent::get_tools(self)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { reads: true, writes: true, shell: true, ..Capabilities::default() }
+    }
+
+    fn model_name(&self) -> Option<&'static str> {
+        Some(Agent::model(self))
+    }
+}
+
+impl Agent for PythonRunner {
+    fn ollama_url(&self) -> &'static str {
+        "http://localhost:11434/api/chat"
+    }
+
+    fn model(&self) -> &'static str {
+        "qwen2.5:14b"
+    }
+
+    fn system_prompt(&self) -> &'static str {
+        SYSTEM_PROMPT
+    }
+
+    fn client(&self) -> Client {
+        Client::new()
+    }
+
+    fn config_name(&self) -> &'static str {
+        Worker::role(self)
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        let tools = vec![
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "run_python".to_string(),
+                    description: "Run a Python snippet in the working directory and return its exit code, stdout and stderr. It is not sandboxed".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "code": {
+                                "type": "string",
+                                "description": "The Python source to run; print() anything you need to see"
+                            }
+                        },
+                        "required": ["code"]
+                    }),
+                },
+            },
+        ];
+
+        // Only expose the tools this deployment enables for the Python runner
+        tools
+            .into_iter()
+            .filter(|tool| config::get().tool_enabled(self.role(), &tool.function.name))
+            .collect()
+    }
+
+    // A snippet can write files as easily as a shell command can
+    fn has_side_effects(&self, name: &str) -> bool {
+        name == "run_python"
+    }
+
+    fn execute_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
+        if !config::get().tool_enabled(self.role(), name) {
+            return Ok(format!("Error: tool '{}' is disabled for {}", name, self.role()));
+        }
+
+        match name {
+            "run_python" => {
+                let code = args["code"].as_str().unwrap_or("");
+                Ok(self.run_python(code))
+            }
+            _ => Ok(format!("Unknown tool: {}", name)),
+        }
+    }
+}
+
+impl PythonRunner {
+    pub fn new(path: Option<&str>) -> Self {
+        let base = match path {
+            Some(p) => fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p)),
+            None => WorkspaceRoot::shared().worker_base("python_runner"),
+        };
+        let config = config::get();
+        let confined = match (config.worker_root("python_runner"), &config.write_root) {
+            (Some(root), _) => Some(format!("python_runner is confined to {}", root.display())),
+            (None, Some(root)) => Some(format!("writes are confined to {}", root.display())),
+            (None, None) => None,
+        };
+        PythonRunner { base, confined }
+    }
+
+    /// Write `code` to a temp file and run it with the configured interpreter under the shell limits
+    /// Blocks until it finishes, so it runs off the async runtime's worker threads
+    fn run_python(&self, code: &str) -> String {
+        if code.trim().is_empty() {
+            return "Error: no code given".to_string();
+        }
+        if let Some(reason) = &self.confined {
+            return format!("Error: run_python is unavailable because {}, and a snippet can't be held to that", reason);
+        }
+        let config = config::get();
+        // Outside the workspace so the snippet can't clobber (or be mistaken for) a project file
+        let script = std::env::temp_dir().join(format!("hive-python-{}.py", Uuid::new_v4()));
+        if let Err(e) = fs::write(&script, code) {
+            return format!("Error writing snippet: {}", e);
+        }
+
+        // -I keeps PYTHON* variables and user site-packages from changing what runs
+        let timeout = (config.python_timeout_secs > 0).then(|| Duration::from_secs(config.python_timeout_secs));
+        let output = blocking::block_in_place(|| {
+            run_limited(&self.base, r#"exec "$0" -I "$1""#, &[OsStr::new(&config.python_interpreter), script.as_os_str()], timeout)
+        });
+        let _ = fs::remove_file(&script);
+        let output = match output {
+            Ok(output) => output,
+            Err(e) => return format!("Error running {}: {}", config.python_interpreter, e),
+        };
+
+        let budget = config.max_result_bytes;
+        let mut result = json!({
+            "exit_code": output.status.code(),
+            "stdout": truncate(String::from_utf8_lossy(&output.stdout).into_owned(), budget),
+            "stderr": truncate(String::from_utf8_lossy(&output.stderr).into_owned(), budget),
+        });
+        if let Some(limit) = output.limit_exceeded() {
+            result["killed"] = json!(format!("snippet was killed after exceeding the {}", limit));
+        }
+        result.to_string()
+    }
+}
+
+const SYSTEM_PROMPT: &str = r#"You are PythonRunner, a specialized Worker in the Hive system that answers questions by running Python.
+
+IMPORTANT: Always respond in English.
+
+# Your Role
+You receive computation tasks from the Queen (calculations, data processing, quick checks) and solve them by running Python snippets. You MUST run code - do not compute results in your head.
+
+# How to Work
+1. Write a short, self-contained snippet that prints what you need
+2. Run it with run_python and read the exit code, stdout and stderr
+3. If it fails, fix the snippet and run it again
+4. Report the result along with the code that produced it
+
+# Operational Guidelines
+- Snippets run in the workspace directory; use relative paths for project files
+- Snippets are not sandboxed: never touch files outside the workspace, and only change files when the Queen asked for it
+- Only the standard library is guaranteed to be available
+- Snippets are killed if they run too long or use too much memory, so keep them small
+
+# Constraints
+- Stay focused on running code
+- Always respond in English
+
+You are careful, empirical, and always run the code before answering."#;
@@ -0,0 +1,627 @@
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use crate::{config, request_id};
+use crate::workspace::WorkspaceRoot;
+use super::normalize;
+use super::limits::{forget_exited_groups, run_limited, spawned_by_hive, truncate};
+use crate::traits::{Capabilities, Worker, WorkerFactory, Agent, RunOptions, Tool, ToolFunction};
+
+inventory::submit! {
+    WorkerFactory(|| Box::new(Shell::new(None)))
+}
+
+pub struct Shell {
+    /// Directory commands run in
+    base: PathBuf,
+    /// Whether `base` came from `worker_roots`, in which case commands may not reach outside it
+    confined: bool,
+}
+
+#[async_trait]
+impl Worker for Shell {
+    fn role(&self) -> &'static str {
+        "shell"
+    }
+
+    fn description(&self) -> &'static str {
+        "Runs shell commands and reports their output and exit status"
+    }
+
+    async fn process(&self, instruction: &str, options: &RunOptions) -> Result<String> {
+        Agent::run(self, &self.preprocess_instruction(instruction), options)
+            .await
+            .map(|result| self.postprocess_output(result))
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        Agent::get_tools(self)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { reads: true, writes: true, shell: true, ..Capabilities::default() }
+    }
+
+    fn model_name(&self) -> Option<&'static str> {
+        Some(Agent::model(self))
+    }
+}
+
+impl Agent for Shell {
+    fn ollama_url(&self) -> &'static str {
+        "http://localhost:11434/api/chat"
+    }
+
+    fn model(&self) -> &'static str {
+        "qwen2.5:14b"
+    }
+
+    fn system_prompt(&self) -> &'static str {
+        SYSTEM_PROMPT
+    }
+
+    fn client(&self) -> Client {
+        Client::new()
+    }
+
+    fn config_name(&self) -> &'static str {
+        Worker::role(self)
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        let tools = vec![
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "execute_command".to_string(),
+                    description: "Run a command with sh -c in the working directory and return its exit code, stdout, stderr and elapsed_ms".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "command": {
+                                "type": "string",
+                                "description": "The shell command to run"
+                            },
+                            "parse": {
+                                "type": "string",
+                                "enum": ["raw", "lines", "json", "columns"],
+                                "description": "How to return stdout: raw text (default), an array of lines, parsed JSON, or whitespace-split columns"
+                            },
+                            "header": {
+                                "type": "boolean",
+                                "description": "With parse=columns, treat the first line as column names and return one object per row"
+                            },
+                            "repeat": {
+                                "type": "integer",
+                                "description": "Run the command this many times (default 1) and report min/max/mean timings; output is from the last run"
+                            }
+                        },
+                        "required": ["command"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "list_processes".to_string(),
+                    description: "List running processes started by hive's commands as JSON [{pid, command, cpu, mem}] (cpu and mem in percent)".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {}
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "kill_process".to_string(),
+                    description: "Send a signal to a process started by hive's commands".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "pid": {
+                                "type": "integer",
+                                "description": "Process id, as shown by list_processes"
+                            },
+                            "signal": {
+                                "type": "string",
+                                "enum": KILL_SIGNALS,
+                                "description": "Signal to send (default TERM; use KILL only if TERM didn't work)"
+                            }
+                        },
+                        "required": ["pid"]
+                    }),
+                },
+            },
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "command_help".to_string(),
+                    description: "Show a command's usage (its --help output or man page, shortened) so you can check its flags before running it".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "command": {
+                                "type": "string",
+                                "description": "Command name, e.g. tar (no path or arguments)"
+                            },
+                            "subcommand": {
+                                "type": "string",
+                                "description": "Optional subcommand, e.g. build for cargo build"
+                            },
+                            "source": {
+                                "type": "string",
+                                "enum": ["help", "man", "auto"],
+                                "description": "--help output, the man page, or --help falling back to man (default: configured)"
+                            }
+                        },
+                        "required": ["command"]
+                    }),
+                },
+            },
+        ];
+
+        // Only expose the tools this deployment enables for the shell
+        tools
+            .into_iter()
+            .filter(|tool| config::get().tool_enabled(self.role(), &tool.function.name))
+            .collect()
+    }
+
+    // Any command might change something, so none are repeated on retry
+    fn has_side_effects(&self, name: &str) -> bool {
+        matches!(name, "execute_command" | "kill_process")
+    }
+
+    fn execute_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
+        if !config::get().tool_enabled(self.role(), name) {
+            return Ok(format!("Error: tool '{}' is disabled for {}", name, self.role()));
+        }
+
+        match name {
+            "execute_command" => {
+                let command = args["command"].as_str().unwrap_or("");
+                let parse = args["parse"].as_str().unwrap_or("raw");
+                let header = args["header"].as_bool().unwrap_or(false);
+                let repeat = args["repeat"].as_u64().unwrap_or(1);
+                if !(1..=MAX_REPEAT).contains(&repeat) {
+                    return Ok(format!("Error: repeat must be between 1 and {}", MAX_REPEAT));
+                }
+                Ok(self.execute_command(command, parse, header, repeat))
+            }
+            "list_processes" => Ok(list_processes()),
+            "kill_process" => {
+                let Some(pid) = args["pid"].as_u64().and_then(|pid| u32::try_from(pid).ok()) else {
+                    return Ok("Error: pid must be a positive integer".to_string());
+                };
+                let signal = args["signal"].as_str().unwrap_or("TERM");
+                Ok(kill_process(pid, signal))
+            }
+            "command_help" => {
+                let command = args["command"].as_str().unwrap_or("");
+                let source = args["source"].as_str().unwrap_or(&config::get().help_source).to_string();
+                Ok(self.command_help(command, args["subcommand"].as_str(), &source))
+            }
+            _ => Ok(format!("Unknown tool: {}", name)),
+        }
+    }
+}
+
+impl Shell {
+    pub fn new(path: Option<&str>) -> Self {
+        let base = match path {
+            Some(p) => fs::canonicalize(p).unwrap_or_else(|_| PathBuf::from(p)),
+            None => WorkspaceRoot::shared().worker_base("shell"),
+        };
+        Shell { base, confined: config::get().worker_root("shell").is_some() }
+    }
+
+    /// First path-like argument in `command` that resolves outside the worker root
+    /// A shell can always find a way out, so this catches honest mistakes rather than hostile commands
+    fn escaping_path<'a>(&self, command: &'a str) -> Option<&'a str> {
+        path_words(command)
+            .filter(|word| word.starts_with('/') || word.starts_with('~') || word.split('/').any(|part| part == ".."))
+            .find(|word| {
+                let expanded = match word.strip_prefix('~') {
+                    Some(rest) => format!("{}{}", std::env::var("HOME").unwrap_or_default(), rest),
+                    None => word.to_string(),
+                };
+                !ALLOWED_OUTSIDE_ROOT.contains(&expanded.as_str()) && !normalize(&self.base.join(&expanded)).starts_with(&self.base)
+            })
+    }
+
+    /// Run `command` and report its output, post-processing stdout according to `parse`
+    /// With `repeat` above 1 it runs that many times (stopping at the first failure) and the last run is reported with timings
+    fn execute_command(&self, command: &str, parse: &str, header: bool, repeat: u64) -> String {
+        if command.trim().is_empty() {
+            return "Error: no command given".to_string();
+        }
+        if self.confined
+            && let Some(path) = self.escaping_path(command)
+        {
+            return format!(
+                "Error: '{}' is outside the shell's root directory '{}'; commands run in that directory, so use paths relative to it",
+                path,
+                self.base.display()
+            );
+        }
+        // Commands run in the base, so absolute paths are never needed there; log them to spot models that keep using them
+        for path in path_words(command).filter(|word| word.starts_with('/') && !ALLOWED_OUTSIDE_ROOT.contains(word)) {
+            let inside = normalize(Path::new(path)).starts_with(&self.base);
+            eprintln!(
+                "[DEBUG{}] Absolute path '{}' in command ({})",
+                request_id::tag(),
+                path,
+                if inside { "inside the root; a relative path would do" } else { "outside the root" }
+            );
+        }
+        let mut timings = Vec::new();
+        let output = loop {
+            let started = Instant::now();
+            let output = match run_limited(&self.base, command, &[], None) {
+                Ok(output) => output,
+                Err(e) => return format!("Error running command: {}", e),
+            };
+            timings.push(started.elapsed());
+            // A failed run says nothing about how long a successful one takes
+            if timings.len() as u64 >= repeat || !output.status.success() || output.limit_exceeded().is_some() {
+                break output;
+            }
+        };
+        let elapsed_ms = timings.last().map_or(0, Duration::as_millis);
+
+        let budget = config::get().max_result_bytes;
+        let stdout = truncate(String::from_utf8_lossy(&output.stdout).into_owned(), budget);
+        let stderr = truncate(String::from_utf8_lossy(&output.stderr).into_owned(), budget);
+        let exit_code = output.status.code();
+        if let Some(limit) = output.limit_exceeded() {
+            return json!({
+                "exit_code": exit_code,
+                "killed": format!("command was killed after exceeding the {}", limit),
+                "stdout": stdout,
+                "stderr": stderr,
+                "elapsed_ms": elapsed_ms,
+            })
+            .to_string();
+        }
+
+        let stdout = match parse {
+            "raw" => json!(stdout),
+            "lines" => json!(stdout.lines().filter(|l| !l.trim().is_empty()).collect::<Vec<_>>()),
+            "json" => match serde_json::from_str::<serde_json::Value>(&stdout) {
+                Ok(value) => value,
+                Err(e) => {
+                    return json!({
+                        "exit_code": exit_code,
+                        "parse_error": format!("stdout is not valid JSON: {}", e),
+                        "stdout": stdout,
+                        "stderr": stderr,
+                        "elapsed_ms": elapsed_ms,
+                    })
+                    .to_string();
+                }
+            },
+            "columns" => parse_columns(&stdout, header),
+            other => return format!("Error: unknown parse mode '{}' (expected raw, lines, json or columns)", other),
+        };
+
+        let mut result = json!({
+            "exit_code": exit_code,
+            "stdout": stdout,
+            "stderr": stderr,
+            "elapsed_ms": elapsed_ms,
+        });
+        if repeat > 1 {
+            result["timing"] = timing_summary(&timings, repeat);
+        }
+        result.to_string()
+    }
+
+    /// Usage text for an allowed command (and subcommand) from `--help`, its man page, or `auto` (man if `--help` gives nothing)
+    fn command_help(&self, command: &str, subcommand: Option<&str>, source: &str) -> String {
+        let valid = |word: &str| !word.is_empty() && word.chars().all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c));
+        if !valid(command) || command.starts_with('-') {
+            return format!("Error: '{}' is not a command name (no paths, arguments or shell syntax)", command);
+        }
+        if let Some(subcommand) = subcommand.filter(|s| !valid(s) || s.starts_with('-')) {
+            return format!("Error: '{}' is not a subcommand name", subcommand);
+        }
+        // `<cmd> <file> --help` would run the file with some commands rather than describe anything
+        if let Some(subcommand) = subcommand.filter(|s| self.base.join(s).exists()) {
+            return format!("Error: '{}' is a file in the working directory, not a subcommand", subcommand);
+        }
+        let allowed = &config::get().help_commands;
+        if allowed.is_empty() {
+            return "Error: command_help is disabled (HIVE_HELP_COMMANDS is empty)".to_string();
+        }
+        if !allowed.iter().any(|c| c == command) {
+            return format!("Error: '{}' is not in the help allowlist ({})", command, allowed.join(", "));
+        }
+        let found = run_limited(&self.base, "command -v \"$0\"", &[OsStr::new(command)], Some(HELP_TIMEOUT));
+        if !found.is_ok_and(|output| output.status.success()) {
+            return format!("Error: '{}' is not installed (not found on PATH)", command);
+        }
+
+        // Names are passed as arguments, never spliced into the script
+        let mut args = vec![OsStr::new(command)];
+        args.extend(subcommand.map(OsStr::new));
+        let help = || self.help_output(r#""$0" "$@" --help 2>&1"#, &args);
+        let man = || {
+            let page = subcommand.map_or(command.to_string(), |sub| format!("{}-{}", command, sub));
+            self.help_output(r#"MANPAGER=cat MANWIDTH=100 man "$0" 2>/dev/null | col -bx"#, &[OsStr::new(&page)])
+        };
+        let text = match source {
+            "help" => help(),
+            "man" => man(),
+            "auto" => help().or_else(man),
+            other => return format!("Error: unknown help source '{}' (expected help, man or auto)", other),
+        };
+        match text {
+            Some(text) => truncate(text, HELP_MAX_BYTES),
+            None => format!("Error: no {} output for '{}'", source, subcommand.map_or(command.to_string(), |sub| format!("{} {}", command, sub))),
+        }
+    }
+
+    /// Output of a help lookup, or `None` if it printed nothing; text cut off by a limit is kept, since it's truncated anyway
+    fn help_output(&self, script: &str, args: &[&OsStr]) -> Option<String> {
+        let output = run_limited(&self.base, script, args, Some(HELP_TIMEOUT)).ok()?;
+        let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        (!text.is_empty()).then_some(text)
+    }
+}
+
+/// Run a configured startup command in the shell's directory under its usual limits, killed after `timeout`
+/// Returns stdout, with a note when the command failed or was cut short
+pub fn context_output(command: &str, timeout: Duration) -> String {
+    let shell = Shell::new(None);
+    if shell.confined
+        && let Some(path) = shell.escaping_path(command)
+    {
+        return format!("Error: '{}' is outside the shell's root directory", path);
+    }
+    let output = match run_limited(&shell.base, command, &[], Some(timeout)) {
+        Ok(output) => output,
+        Err(e) => return format!("Error running command: {}", e),
+    };
+    let stdout = truncate(String::from_utf8_lossy(&output.stdout).into_owned(), config::get().max_result_bytes);
+    if let Some(limit) = output.limit_exceeded() {
+        return format!("{}\n[killed after exceeding the {}]", stdout, limit);
+    }
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let status = output.status.code().map_or("a signal".to_string(), |code| format!("exit code {}", code));
+        return match stderr.trim() {
+            "" => format!("{}\n[failed with {}]", stdout, status),
+            stderr => format!("{}\n[failed with {}: {}]", stdout, status, stderr),
+        };
+    }
+    stdout
+}
+
+/// Words of `command` that could be paths: split on whitespace and shell operators, unquoted,
+/// and with any `NAME=` prefix dropped
+fn path_words(command: &str) -> impl Iterator<Item = &str> {
+    command
+        .split(|c: char| c.is_whitespace() || matches!(c, ';' | '|' | '&' | '<' | '>' | '(' | ')' | '`'))
+        .map(|word| word.trim_matches(['"', '\'']))
+        .map(|word| word.rsplit_once('=').map_or(word, |(_, value)| value))
+}
+
+/// Min/max/mean of a repeated command's runs, noting when it stopped short of `repeat`
+fn timing_summary(timings: &[Duration], repeat: u64) -> serde_json::Value {
+    let millis: Vec<f64> = timings.iter().map(|t| t.as_secs_f64() * 1000.0).collect();
+    let round = |ms: f64| (ms * 10.0).round() / 10.0;
+    let mut summary = json!({
+        "runs": millis.len(),
+        "min_ms": round(millis.iter().copied().fold(f64::INFINITY, f64::min)),
+        "max_ms": round(millis.iter().copied().fold(0.0, f64::max)),
+        "mean_ms": round(millis.iter().sum::<f64>() / millis.len() as f64),
+    });
+    if (millis.len() as u64) < repeat {
+        summary["stopped_early"] = json!(format!("run {} of {} failed", millis.len(), repeat));
+    }
+    summary
+}
+
+/// Running processes hive may manage, as `{pid, command, cpu, mem}`
+fn list_processes() -> String {
+    let running = match running_processes() {
+        Ok(running) => running,
+        Err(e) => return e,
+    };
+    let manage_all = config::get().manage_all_processes;
+    let processes: Vec<_> = running
+        .into_iter()
+        .filter(|process| manage_all || spawned_by_hive(process.pgid))
+        .map(|process| json!({ "pid": process.pid, "command": process.command, "cpu": process.cpu, "mem": process.mem }))
+        .collect();
+    if processes.is_empty() {
+        return "No processes started by hive are running".to_string();
+    }
+    truncate(json!(processes).to_string(), config::get().max_result_bytes)
+}
+
+/// Send `signal` to `pid`, provided hive started it (or may manage any process)
+fn kill_process(pid: u32, signal: &str) -> String {
+    let signal = signal.trim_start_matches("SIG").to_ascii_uppercase();
+    if !KILL_SIGNALS.contains(&signal.as_str()) {
+        return format!("Error: unsupported signal '{}' (expected one of {})", signal, KILL_SIGNALS.join(", "));
+    }
+    if pid == std::process::id() {
+        return "Error: refusing to signal hive itself".to_string();
+    }
+
+    let running = match running_processes() {
+        Ok(running) => running,
+        Err(e) => return e,
+    };
+    let Some(process) = running.into_iter().find(|process| process.pid == pid) else {
+        return format!("Error: no process with pid {}", pid);
+    };
+    if !config::get().manage_all_processes && !spawned_by_hive(process.pgid) {
+        return format!(
+            "Error: process {} was not started by hive; set HIVE_MANAGE_ALL_PROCESSES=true to manage other processes",
+            pid
+        );
+    }
+
+    match Command::new("kill").args(["-s", &signal, &pid.to_string()]).output() {
+        Ok(output) if output.status.success() => format!("Sent SIG{} to process {} ({})", signal, pid, process.command),
+        Ok(output) => format!("Error: kill failed: {}", String::from_utf8_lossy(&output.stderr).trim()),
+        Err(e) => format!("Error running kill: {}", e),
+    }
+}
+
+/// Every live process, from `ps`; hive's groups that have none left are forgotten along the way
+fn running_processes() -> Result<Vec<ProcessInfo>, String> {
+    let output = match Command::new("ps").args(["-eo", "pid=,pgid=,stat=,pcpu=,pmem=,args="]).output() {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => return Err(format!("Error: ps failed: {}", String::from_utf8_lossy(&output.stderr).trim())),
+        Err(e) => return Err(format!("Error running ps: {}", e)),
+    };
+    // Exited processes linger as zombies until reaped; there's nothing left to manage
+    let running: Vec<_> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(parse_ps_line)
+        .filter(|process| !process.state.starts_with('Z'))
+        .collect();
+    forget_exited_groups(&running.iter().map(|process| process.pgid).collect());
+    Ok(running)
+}
+
+/// One row of `ps -o pid=,pgid=,stat=,pcpu=,pmem=,args=`
+struct ProcessInfo {
+    pid: u32,
+    pgid: u32,
+    state: String,
+    cpu: f64,
+    mem: f64,
+    command: String,
+}
+
+/// Parse a `ps` row; the command is everything after the fifth column, spaces included
+fn parse_ps_line(line: &str) -> Option<ProcessInfo> {
+    let mut rest = line.trim_start();
+    let mut fields = [""; 5];
+    for field in &mut fields {
+        let end = rest.find(char::is_whitespace)?;
+        *field = &rest[..end];
+        rest = rest[end..].trim_start();
+    }
+    Some(ProcessInfo {
+        pid: fields[0].parse().ok()?,
+        pgid: fields[1].parse().ok()?,
+        state: fields[2].to_string(),
+        cpu: fields[3].parse().ok()?,
+        mem: fields[4].parse().ok()?,
+        command: rest.to_string(),
+    })
+}
+
+/// Split each line on whitespace; with `header`, key rows by the first line's names
+/// The last column keeps any remaining text, so trailing fields with spaces (file names, commands) stay whole
+fn parse_columns(text: &str, header: bool) -> serde_json::Value {
+    let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+    if !header {
+        let rows: Vec<Vec<&str>> = lines.map(|l| l.split_whitespace().collect()).collect();
+        return json!(rows);
+    }
+
+    let Some(names) = lines.next() else {
+        return json!([]);
+    };
+    let names: Vec<&str> = names.split_whitespace().collect();
+    let rows: Vec<serde_json::Value> = lines
+        .map(|line| {
+            let mut row = serde_json::Map::new();
+            let mut rest = line.trim_start();
+            for (i, name) in names.iter().enumerate() {
+                let value = if i + 1 == names.len() {
+                    std::mem::take(&mut rest).trim_end()
+                } else {
+                    let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                    let (value, remainder) = rest.split_at(end);
+                    rest = remainder.trim_start();
+                    value
+                };
+                row.insert(name.to_string(), json!(value));
+            }
+            serde_json::Value::Object(row)
+        })
+        .collect();
+    json!(rows)
+}
+
+/// Paths a confined shell may still name, since redirecting to them touches nothing
+const ALLOWED_OUTSIDE_ROOT: &[&str] = &["/dev/null", "/dev/stdout", "/dev/stderr"];
+
+/// Most times a single execute_command call may run its command
+const MAX_REPEAT: u64 = 100;
+
+/// Signals `kill_process` may send
+const KILL_SIGNALS: &[&str] = &["TERM", "INT", "HUP", "KILL", "STOP", "CONT"];
+
+/// Longest a `command_help` lookup may take
+const HELP_TIMEOUT: Duration = Duration::from_secs(10);
+/// Most usage text `command_help` returns; man pages run far longer than a flag lookup needs
+const HELP_MAX_BYTES: usize = 8_000;
+
+const SYSTEM_PROMPT: &str = r#"You are Shell, a specialized Worker in the Hive system that runs shell commands.
+
+IMPORTANT: Always respond in English.
+
+# Your Role
+You receive command-line tasks from the Queen and carry them out with the execute_command tool. You MUST run commands to complete tasks - do not guess at their output.
+
+# How to Work
+1. Run the command with execute_command
+2. For listings and tables (ls -la, ps, df), ask for parse "columns" with header true when the first line names the columns
+3. For commands that print JSON, ask for parse "json"
+4. Check the exit code and stderr before reporting success
+5. To time a command, pass repeat (e.g. 5) and report the min/max/mean from timing
+6. To stop something a previous command left running, find it with list_processes and stop it with kill_process
+7. When unsure of a command's flags, look them up with command_help instead of guessing
+
+# Operational Guidelines
+- Prefer read-only commands unless the Queen asked for a change
+- Never run interactive commands (editors, pagers, prompts)
+- Report the exit code and any errors clearly
+
+# Constraints
+- Stay focused on running commands
+- Always respond in English
+
+You are careful, precise, and always run commands to complete tasks."#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_columns_keeps_the_last_column_whole() {
+        let table = parse_columns("PID  COMMAND\n1    sleep 10\n\n22   /bin/my app  \n", true);
+        assert_eq!(table, json!([{"PID": "1", "COMMAND": "sleep 10"}, {"PID": "22", "COMMAND": "/bin/my app"}]));
+        assert_eq!(parse_columns("PID COMMAND\n", true), json!([]));
+        assert_eq!(parse_columns("", true), json!([]));
+    }
+
+    #[test]
+    fn parse_columns_without_a_header_splits_every_field() {
+        assert_eq!(parse_columns("a  b\nc\n", false), json!([["a", "b"], ["c"]]));
+    }
+
+    #[test]
+    fn parse_ps_line_keeps_the_command_whole() {
+        let info = parse_ps_line("  42  40 S    1.5  0.3 python3 -m http.server 8000").unwrap();
+        assert_eq!((info.pid, info.pgid, info.state.as_str()), (42, 40, "S"));
+        assert_eq!((info.cpu, info.mem), (1.5, 0.3));
+        assert_eq!(info.command, "python3 -m http.server 8000");
+        assert!(parse_ps_line("42 40 S").is_none());
+    }
+}
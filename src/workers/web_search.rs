@@ -0,0 +1,228 @@
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use tokio::runtime::Handle;
+use crate::config;
+use crate::traits::{Capabilities, Worker, WorkerFactory, Agent, RunOptions, Tool, ToolFunction};
+
+inventory::submit! {
+    WorkerFactory(|| Box::new(WebSearch::new()))
+}
+
+/// Where search queries go; add a variant here to support another provider
+pub enum SearchBackend {
+    /// A (usually self-hosted) SearXNG instance with the JSON output format enabled
+    Searxng { url: String },
+    /// The Brave Search API
+    Brave { api_key: String },
+}
+
+impl SearchBackend {
+    /// Backend described by the `search_*` config, if one is configured
+    pub fn from_config() -> Option<SearchBackend> {
+        let config = config::get();
+        match config.search_provider.as_str() {
+            "brave" => config.search_api_key.clone().map(|api_key| SearchBackend::Brave { api_key }),
+            _ => config.search_url.clone().map(|url| SearchBackend::Searxng { url }),
+        }
+    }
+
+    /// Top `count` results as `{title, url, snippet}`
+    async fn search(&self, client: &Client, query: &str, count: usize) -> Result<Vec<serde_json::Value>> {
+        let (response, results_key, snippet_key) = match self {
+            SearchBackend::Searxng { url } => {
+                let endpoint = format!("{}/search", url.trim_end_matches('/'));
+                let response = client.get(endpoint).query(&[("q", query), ("format", "json")]).send().await?;
+                (response, "/results", "content")
+            }
+            SearchBackend::Brave { api_key } => {
+                let response = client
+                    .get("https://api.search.brave.com/res/v1/web/search")
+                    .header("X-Subscription-Token", api_key)
+                    .query(&[("q", query), ("count", &count.to_string())])
+                    .send()
+                    .await?;
+                (response, "/web/results", "description")
+            }
+        };
+
+        let status = response.status();
+        if !status.is_success() {
+            bail!("search backend returned {}: {}", status, response.text().await.unwrap_or_default());
+        }
+        let body: serde_json::Value = response.json().await?;
+        let results = body
+            .pointer(results_key)
+            .and_then(|r| r.as_array())
+            .map(|results| {
+                results
+                    .iter()
+                    .take(count)
+                    .map(|r| json!({ "title": r["title"], "url": r["url"], "snippet": r[snippet_key] }))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Ok(results)
+    }
+}
+
+pub struct WebSearch {
+    backend: Option<SearchBackend>,
+    client: Client,
+}
+
+#[async_trait]
+impl Worker for WebSearch {
+    fn role(&self) -> &'static str {
+        "web_search"
+    }
+
+    fn description(&self) -> &'static str {
+        "Searches the web and returns the top results (title, URL, snippet) for a query"
+    }
+
+    async fn process(&self, instruction: &str, options: &RunOptions) -> Result<String> {
+        Agent::run(self, &self.preprocess_instruction(instruction), options)
+            .await
+            .map(|result| self.postprocess_output(result))
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        Agent::get_tools(self)
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities { network: true, ..Capabilities::default() }
+    }
+
+    fn model_name(&self) -> Option<&'static str> {
+        Some(Agent::model(self))
+    }
+}
+
+impl Agent for WebSearch {
+    fn ollama_url(&self) -> &'static str {
+        "http://localhost:11434/api/chat"
+    }
+
+    fn model(&self) -> &'static str {
+        "qwen2.5:14b"
+    }
+
+    fn system_prompt(&self) -> &'static str {
+        SYSTEM_PROMPT
+    }
+
+    fn client(&self) -> Client {
+        Client::new()
+    }
+
+    fn config_name(&self) -> &'static str {
+        Worker::role(self)
+    }
+
+    fn get_tools(&self) -> Vec<Tool> {
+        let tools = vec![
+            Tool {
+                tool_type: "function".to_string(),
+                function: ToolFunction {
+                    name: "search".to_string(),
+                    description: "Search the web and return the top results as JSON [{title, url, snippet}]".to_string(),
+                    parameters: json!({
+                        "type": "object",
+                        "properties": {
+                            "query": {
+                                "type": "string",
+                                "description": "What to search for"
+                            },
+                            "count": {
+                                "type": "integer",
+                                "description": "How many results to return (default 5, at most 20)"
+                            }
+                        },
+                        "required": ["query"]
+                    }),
+                },
+            },
+        ];
+
+        // Only expose the tools this deployment enables for web search
+        tools
+            .into_iter()
+            .filter(|tool| config::get().tool_enabled(self.role(), &tool.function.name))
+            .collect()
+    }
+
+    fn execute_tool(&self, name: &str, args: &serde_json::Value) -> Result<String> {
+        if !config::get().tool_enabled(self.role(), name) {
+            return Ok(format!("Error: tool '{}' is disabled for {}", name, self.role()));
+        }
+
+        match name {
+            "search" => {
+                let query = args["query"].as_str().unwrap_or("");
+                let count = args["count"].as_u64().unwrap_or(5).clamp(1, MAX_RESULTS) as usize;
+                let Some(backend) = &self.backend else {
+                    return Ok("Error: no search backend configured (set HIVE_SEARCH_URL to a SearXNG instance, \
+                        or HIVE_SEARCH_PROVIDER=brave with HIVE_SEARCH_API_KEY)"
+                        .to_string());
+                };
+                if query.trim().is_empty() {
+                    return Ok("Error: empty search query".to_string());
+                }
+                // Tools run synchronously, so wait for the request without stalling other tasks
+                let results = tokio::task::block_in_place(|| {
+                    Handle::current().block_on(backend.search(&self.client, query, count))
+                });
+                match results {
+                    Ok(results) if results.is_empty() => Ok(format!("No results for '{}'", query)),
+                    Ok(results) => Ok(json!(results).to_string()),
+                    Err(e) => Ok(format!("Error searching: {}", e)),
+                }
+            }
+            _ => Ok(format!("Unknown tool: {}", name)),
+        }
+    }
+}
+
+impl WebSearch {
+    pub fn new() -> Self {
+        WebSearch::with_backend(SearchBackend::from_config())
+    }
+
+    pub fn with_backend(backend: Option<SearchBackend>) -> Self {
+        WebSearch { backend, client: Client::new() }
+    }
+}
+
+impl Default for WebSearch {
+    fn default() -> Self {
+        WebSearch::new()
+    }
+}
+
+/// Most results a single `search` call may return
+const MAX_RESULTS: u64 = 20;
+
+const SYSTEM_PROMPT: &str = r#"You are WebSearch, a specialized Worker in the Hive system that finds information on the web.
+
+IMPORTANT: Always respond in English.
+
+# Your Role
+You receive search tasks from the Queen and answer them using the search tool. You MUST search - do not answer from memory or invent URLs.
+
+# How to Work
+1. Turn the Queen's request into a focused search query
+2. Run the search tool; refine the query and search again if the results miss the point
+3. Report the most relevant results with their URLs so the Queen can fetch them
+
+# Operational Guidelines
+- Quote URLs exactly as returned by the search tool
+- Say clearly when nothing relevant was found
+
+# Constraints
+- Stay focused on searching
+- Always respond in English
+
+You are thorough, accurate, and always search before answering."#;
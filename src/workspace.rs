@@ -0,0 +1,67 @@
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock, RwLock};
+use std::{fs, io};
+use crate::config;
+
+/// Sentinel file in the workspace root; while it exists, tools with side effects are refused
+pub const STOP_FILE: &str = ".hive-stop";
+
+/// The directory every worker operates in, resolved once so they can't drift apart
+#[derive(Clone, Debug)]
+pub struct WorkspaceRoot(Arc<PathBuf>);
+
+impl WorkspaceRoot {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        // Canonical so sandbox checks compare absolute paths
+        WorkspaceRoot(Arc::new(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())))
+    }
+
+    /// Process-wide root, captured from the current directory on first use
+    pub fn shared() -> WorkspaceRoot {
+        shared_root().read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Point the whole session at `path`, taken relative to the current root
+    /// The process's current directory is left alone; anything that runs in the workspace is handed
+    /// the root explicitly. Workers built before this keep their old root, so the Queen rebuilds them afterwards
+    pub fn set_shared(path: &Path) -> io::Result<WorkspaceRoot> {
+        let path = fs::canonicalize(Self::shared().join(path))?;
+        if !path.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::NotADirectory, format!("{} is not a directory", path.display())));
+        }
+        let root = WorkspaceRoot(Arc::new(path));
+        *shared_root().write().unwrap_or_else(|e| e.into_inner()) = root.clone();
+        Ok(root)
+    }
+
+    /// Directory `role` is confined to: its configured root under the workspace, or the workspace itself
+    pub fn worker_base(&self, role: &str) -> PathBuf {
+        match config::get().worker_root(role) {
+            Some(root) => {
+                let base = self.join(root);
+                fs::canonicalize(&base).unwrap_or(base)
+            }
+            None => self.to_path_buf(),
+        }
+    }
+
+    /// Whether an operator has paused side effects by creating `STOP_FILE`
+    pub fn stop_requested(&self) -> bool {
+        self.join(STOP_FILE).exists()
+    }
+}
+
+fn shared_root() -> &'static RwLock<WorkspaceRoot> {
+    static ROOT: OnceLock<RwLock<WorkspaceRoot>> = OnceLock::new();
+    ROOT.get_or_init(|| RwLock::new(WorkspaceRoot::new(".")))
+}
+
+impl Deref for WorkspaceRoot {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}